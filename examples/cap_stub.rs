@@ -0,0 +1,29 @@
+//! Cap router handing itself out as the root bucket, which implements no bucket method, so every insert
+//! of the canisters pointed at it fails. Lets the tests reach the cap outbox backoff, see `cap-stub` in dfx.json.
+
+use ic_cdk::export::candid::{CandidType, Deserialize};
+use ic_cdk::export::Principal;
+use ic_cdk_macros::query;
+
+#[derive(CandidType, Deserialize)]
+struct GetTokenContractRootBucketArg {
+    canister: Principal,
+    witness: bool,
+}
+
+// `witness` is optional in the router response, it's left out
+#[derive(CandidType)]
+struct GetTokenContractRootBucketResponse {
+    canister: Option<Principal>,
+}
+
+#[query]
+fn get_token_contract_root_bucket(
+    _arg: GetTokenContractRootBucketArg,
+) -> GetTokenContractRootBucketResponse {
+    GetTokenContractRootBucketResponse {
+        canister: Some(ic_cdk::id()),
+    }
+}
+
+fn main() {}
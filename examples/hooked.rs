@@ -0,0 +1,135 @@
+//! Canister registering `hooks`, built without the `lifecycle` feature so it exports the lifecycle
+//! methods itself, see `nft-hooked` in dfx.json. With `lifecycle` it only prints the candid.
+//! Mints of nfts with a `vetoed` property, transfers of nfts with a `locked` property and transfers out
+//! of the canister itself (it doesn't accept notified transfers) are vetoed, the after hooks are logged and
+//! returned by `hook_log`.
+
+extern crate dip721;
+
+#[cfg(not(feature = "lifecycle"))]
+mod canister {
+    use dip721::hooks::{self, Hooks};
+    use dip721::ledger::Ledger;
+    use dip721::{Account, GenericValue, InitArgs, NftError, TokenIdentifier, UpgradeArgs};
+    use ic_cdk::export::Principal;
+    use ic_cdk_macros::{init, post_upgrade, pre_upgrade, query};
+    use std::cell::RefCell;
+
+    thread_local!(
+        static HOOK_LOG: RefCell<Vec<String>> = const { RefCell::new(vec![]) };
+    );
+
+    fn log(entry: String) {
+        HOOK_LOG.with(|hook_log| hook_log.borrow_mut().push(entry));
+    }
+
+    struct ExampleHooks;
+
+    impl Hooks for ExampleHooks {
+        fn before_mint(
+            &self,
+            _ledger: &Ledger,
+            _caller: Principal,
+            _to: &Principal,
+            _token_identifier: &TokenIdentifier,
+            properties: &[(String, GenericValue)],
+        ) -> Result<(), NftError> {
+            if properties.iter().any(|(key, _)| key == "vetoed") {
+                return Err(NftError::Other("mint vetoed".into()));
+            }
+            Ok(())
+        }
+
+        fn after_mint(
+            &self,
+            _ledger: &Ledger,
+            _caller: Principal,
+            to: &Principal,
+            token_identifier: &TokenIdentifier,
+        ) {
+            log(format!("mint {} to {}", token_identifier, to));
+        }
+
+        fn before_transfer(
+            &self,
+            ledger: &Ledger,
+            _caller: Principal,
+            from: &Account,
+            _to: &Account,
+            token_identifier: &TokenIdentifier,
+        ) -> Result<(), NftError> {
+            if from.owner == ic_cdk::id() {
+                return Err(NftError::Other("transfer vetoed".into()));
+            }
+            let token_metadata = ledger.token_metadata(token_identifier)?;
+            if token_metadata
+                .properties
+                .iter()
+                .any(|(key, _)| key == "locked")
+            {
+                return Err(NftError::Other("transfer vetoed".into()));
+            }
+            Ok(())
+        }
+
+        fn after_transfer(
+            &self,
+            _ledger: &Ledger,
+            _caller: Principal,
+            from: &Account,
+            to: &Account,
+            token_identifier: &TokenIdentifier,
+        ) {
+            log(format!(
+                "transfer {} from {} to {}",
+                token_identifier, from.owner, to.owner
+            ));
+        }
+
+        fn after_burn(
+            &self,
+            _ledger: &Ledger,
+            _caller: Principal,
+            owner: &Account,
+            token_identifier: &TokenIdentifier,
+        ) {
+            log(format!("burn {} of {}", token_identifier, owner.owner));
+        }
+    }
+
+    #[init]
+    fn init(args: Option<InitArgs>) {
+        dip721::init(args);
+        hooks::set(ExampleHooks);
+    }
+
+    // every lifecycle method has to be exported, the library doesn't export any without `lifecycle`
+    #[pre_upgrade]
+    fn pre_upgrade() {
+        dip721::pre_upgrade();
+    }
+
+    #[post_upgrade]
+    fn post_upgrade(args: Option<UpgradeArgs>) {
+        dip721::post_upgrade(args);
+        hooks::set(ExampleHooks);
+    }
+
+    #[export_name = "canister_global_timer"]
+    fn global_timer() {
+        dip721::global_timer();
+    }
+
+    #[query]
+    fn hook_log() -> Vec<String> {
+        HOOK_LOG.with(|hook_log| hook_log.borrow().clone())
+    }
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+fn main() {}
+
+#[cfg(not(any(target_arch = "wasm32", test)))]
+fn main() {
+    std::print!("{}", dip721::export_candid());
+}
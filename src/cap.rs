@@ -0,0 +1,394 @@
+//! Outbox of the events forwarded to cap.
+//! Every tx is queued in stable memory when recorded and removed once the cap bucket accepted it,
+//! the global timer flushes the queue in tx order and backs off while cap is unreachable or out of cycles,
+//! so the cap history catches up with `tx_count` instead of silently missing events.
+//! Cap numbers its txs itself, every insert checks the number matches the tx id. The first mismatch
+//! halts the flushes, even across upgrades, and keeps its event queued until a re-sync realigns cap.
+//!
+//! The root bucket is looked up on the cap router of the metadata by the first flush, and created
+//! and installed by the router when it has none for this canister. Events are inserted into it directly.
+//!
+//! A lost root bucket or a new cap router is caught up by a re-sync, requeueing the logged txs
+//! a chunk at a time while flushes wait for it to reach `tx_count`. Re-syncs are refused while a
+//! flush awaits cap, the queue it reads would change under it.
+
+use crate::*;
+use cap_sdk::IndefiniteEvent;
+use ic_cdk::api::call::call_with_payment;
+use ic_cdk::api::canister_balance;
+
+const MAINNET_ROUTER: &str = "lj532-6iaaa-aaaah-qcc7a-cai";
+const CREATION_CYCLES: u64 = 1_000_000_000_000; // for the root bucket of new collections
+const MIN_BALANCE: u64 = 1_000_000_000_000; // left on the canister once the root bucket is created
+const MAX_BATCH_SIZE: usize = 100;
+const MAX_RESYNC_SIZE: u32 = 1000;
+const RETRY_DELAY: u64 = 10 * 1_000_000_000; // 10s in ns, doubled on every failure
+const MAX_RETRY_DELAY: u64 = 60 * 60 * 1_000_000_000; // 1h in ns
+
+#[derive(Default)]
+struct Status {
+    flushing: bool,
+    failed: u32,
+    retry_at: u64,
+    last_error: Option<String>,
+    root_bucket: Option<Principal>, // looked up on the router again after upgrades and switches
+    created_bucket: Option<Principal>, // created for the router, which didn't install it yet
+}
+
+thread_local!(
+    // not persisted but for `created_bucket`, an upgrade retries right away
+    static STATUS: RefCell<Status> = RefCell::default();
+);
+
+#[derive(CandidType, Deserialize)]
+struct GetTokenContractRootBucketArg {
+    canister: Principal,
+    witness: bool,
+}
+
+// `witness` is left out, it's never asked for
+#[derive(CandidType, Deserialize)]
+struct GetTokenContractRootBucketResponse {
+    canister: Option<Principal>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct CanisterSettings {
+    controllers: Option<Vec<Principal>>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct CreateCanisterArgument {
+    settings: Option<CanisterSettings>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct CanisterIdRecord {
+    canister_id: Principal,
+}
+
+fn retry_delay(failed: u32) -> u64 {
+    RETRY_DELAY
+        .saturating_mul(1 << failed.saturating_sub(1).min(16))
+        .min(MAX_RETRY_DELAY)
+}
+
+fn stats() -> CapOutboxStats {
+    STATUS.with(|status| {
+        let status = status.borrow();
+        CapOutboxStats {
+            pending: ledger::with(|ledger| ledger.cap_outbox_len()),
+            failed: status.failed,
+            retry_at: (status.retry_at > 0).then(|| status.retry_at),
+            last_error: ledger::with(|ledger| ledger.cap_misaligned())
+                .map(|(tx_id, cap_tx_id)| {
+                    format!(
+                        "cap recorded tx {} as tx {}, flushes halt until a re-sync",
+                        tx_id, cap_tx_id
+                    )
+                })
+                .or_else(|| status.last_error.clone()),
+            resync_cursor: ledger::with(|ledger| ledger.cap_resync()).map(|(_, cursor)| cursor),
+        }
+    })
+}
+
+/// Root bucket created for the router but not installed yet, saved across upgrades so it isn't created twice.
+pub fn created_bucket() -> Option<Principal> {
+    STATUS.with(|status| status.borrow().created_bucket)
+}
+
+pub fn restore_created_bucket(created_bucket: Option<Principal>) {
+    STATUS.with(|status| status.borrow_mut().created_bucket = created_bucket);
+}
+
+/// Points the outbox at the cap router `router`, the mainnet one when `None`. The root bucket it hands out
+/// is looked up by the next flush, the backoff is kept. Refused while a flush awaits cap.
+pub fn set_router(router: Option<Principal>) {
+    if STATUS.with(|status| status.borrow().flushing) {
+        trap("a cap flush is running, switch the router once it's over");
+    }
+    ledger::with_mut(|ledger| {
+        if !ledger.history().forwards_to_cap() {
+            trap("txs aren't forwarded to cap with this history backend");
+        }
+        ledger.metadata_mut().cap = router;
+    });
+    STATUS.with(|status| {
+        let mut status = status.borrow_mut();
+        status.root_bucket = None;
+        // owned by the previous router
+        status.created_bucket = None;
+    });
+    if is_pending() {
+        schedule();
+    }
+}
+
+fn router() -> Principal {
+    ledger::with(|ledger| ledger.metadata().cap)
+        .unwrap_or_else(|| Principal::from_text(MAINNET_ROUTER).expect("invalid mainnet router"))
+}
+
+async fn lookup_root_bucket(router: Principal) -> Result<Option<Principal>, String> {
+    let (response,): (GetTokenContractRootBucketResponse,) = ic_cdk::call(
+        router,
+        "get_token_contract_root_bucket",
+        (GetTokenContractRootBucketArg {
+            canister: ic_cdk::id(),
+            witness: false,
+        },),
+    )
+    .await
+    .map_err(|(_, message)| {
+        format!(
+            "cap router {} rejected the root bucket lookup: {}",
+            router, message
+        )
+    })?;
+    Ok(response.canister)
+}
+
+/// Creates a canister controlled by `router` for it to install the root bucket in.
+async fn create_bucket(router: Principal) -> Result<Principal, String> {
+    if canister_balance() < CREATION_CYCLES.saturating_add(MIN_BALANCE) {
+        return Err("not enough cycles to create a cap root bucket".into());
+    }
+    let (created,): (CanisterIdRecord,) = call_with_payment(
+        Principal::management_canister(),
+        "create_canister",
+        (CreateCanisterArgument {
+            settings: Some(CanisterSettings {
+                controllers: Some(vec![router]),
+            }),
+        },),
+        CREATION_CYCLES,
+    )
+    .await
+    .map_err(|(_, message)| format!("couldn't create a cap root bucket: {}", message))?;
+    Ok(created.canister_id)
+}
+
+/// Root bucket of this canister on the cap router, created and installed when the router has none.
+async fn root_bucket() -> Result<Principal, String> {
+    if let Some(root_bucket) = STATUS.with(|status| status.borrow().root_bucket) {
+        return Ok(root_bucket);
+    }
+    let router = router();
+    let root_bucket = match lookup_root_bucket(router).await? {
+        Some(root_bucket) => root_bucket,
+        None => {
+            let created_bucket = match created_bucket() {
+                Some(created_bucket) => created_bucket,
+                None => {
+                    let created_bucket = create_bucket(router).await?;
+                    restore_created_bucket(Some(created_bucket));
+                    created_bucket
+                }
+            };
+            ic_cdk::call::<_, ()>(router, "install_bucket_code", (created_bucket,))
+                .await
+                .map_err(|(_, message)| {
+                    format!(
+                        "cap router {} didn't install the root bucket: {}",
+                        router, message
+                    )
+                })?;
+            restore_created_bucket(None);
+            lookup_root_bucket(router).await?.ok_or_else(|| {
+                format!(
+                    "cap router {} has no root bucket after installing it",
+                    router
+                )
+            })?
+        }
+    };
+    STATUS.with(|status| status.borrow_mut().root_bucket = Some(root_bucket));
+    Ok(root_bucket)
+}
+
+async fn insert(event: IndefiniteEvent) -> Result<u64, String> {
+    let root_bucket = root_bucket().await?;
+    let (cap_tx_id,): (u64,) = ic_cdk::call(root_bucket, "insert", (event,))
+        .await
+        .map_err(|(_, message)| {
+            format!(
+                "cap root bucket {} rejected the insert: {}",
+                root_bucket, message
+            )
+        })?;
+    Ok(cap_tx_id)
+}
+
+/// Arms the timer for the next flush, once the backoff is over.
+/// A running flush arms it itself when it ends.
+pub fn schedule() {
+    STATUS.with(|status| {
+        let status = status.borrow();
+        if !status.flushing {
+            timer::schedule(status.retry_at.max(time()));
+        }
+    });
+}
+
+fn is_pending() -> bool {
+    ledger::with(|ledger| {
+        ledger.cap_outbox_len() > 0
+            && ledger.cap_resync().is_none()
+            && ledger.cap_misaligned().is_none()
+    })
+}
+
+/// Starts a flush when events are pending, no flush is running and the backoff is over.
+pub fn flush() {
+    let is_due = STATUS.with(|status| {
+        let status = status.borrow();
+        !status.flushing && status.retry_at <= time()
+    });
+    if is_due && is_pending() {
+        ic_cdk::spawn(flush_now());
+    }
+}
+
+/// Inserts the pending events one at a time so cap keeps the tx order, stops at the first failure
+/// or numbering mismatch.
+async fn flush_now() {
+    let is_flushing = STATUS.with(|status| {
+        let mut status = status.borrow_mut();
+        std::mem::replace(&mut status.flushing, true)
+    });
+    if is_flushing {
+        return;
+    }
+    for _ in 0..MAX_BATCH_SIZE {
+        if !is_pending() {
+            break;
+        }
+        // the head is read again after every insert, the queue may have changed meanwhile
+        let (tx_id, tx_event) = match ledger::with(|ledger| ledger.cap_outbox(1).pop()) {
+            Some(head) => head,
+            None => break,
+        };
+        let result = insert(IndefiniteEvent {
+            caller: tx_event.caller,
+            operation: tx_event.operation,
+            details: tx_event
+                .details
+                .into_iter()
+                .map(|(key, value)| (key, DetailValue::from(value)))
+                .collect(),
+        })
+        .await;
+        match result {
+            Ok(cap_tx_id) => {
+                STATUS.with(|status| {
+                    let mut status = status.borrow_mut();
+                    status.failed = 0;
+                    status.retry_at = 0;
+                    status.last_error = None;
+                });
+                let cap_tx_id = Nat::from(cap_tx_id);
+                if cap_tx_id != tx_id {
+                    // the following events would be misnumbered too
+                    ledger::with_mut(|ledger| ledger.set_cap_misaligned(Some((tx_id, cap_tx_id))));
+                    break;
+                }
+                ledger::with_mut(|ledger| ledger.remove_from_cap_outbox(&tx_id));
+            }
+            Err(err) => {
+                STATUS.with(|status| {
+                    let mut status = status.borrow_mut();
+                    status.failed = status.failed.saturating_add(1);
+                    status.retry_at = time().saturating_add(retry_delay(status.failed));
+                    status.last_error = Some(err);
+                });
+                break;
+            }
+        }
+    }
+    STATUS.with(|status| status.borrow_mut().flushing = false);
+    if is_pending() {
+        schedule();
+    }
+}
+
+// ==================================================================================================
+// cap outbox
+// ==================================================================================================
+#[query()]
+#[candid_method(query)]
+fn dip721_cap_outbox() -> CapOutboxStats {
+    stats()
+}
+
+/// Flushes the outbox now, the backoff is skipped. Returns once the flush is over,
+/// right away when one is already running.
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+async fn dip721_flush_cap_outbox() -> CapOutboxStats {
+    flush_now().await;
+    stats()
+}
+
+/// Replays up to `limit` logged txs into cap, starting from tx id `from`, or resuming the running
+/// re-sync when `None`. Returns the next tx id to replay, the re-sync is over once `dip721_cap_outbox`
+/// reports no `resync_cursor`.
+/// The root bucket must hold exactly the txs before `from`, `Some(0)` for a new one. Txs only cap holds,
+/// those recorded before an upgrade from the heap ledger, can't be replayed.
+/// Rejected while a flush awaits cap, a re-sync from `Some` also lifts a numbering mismatch halt.
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+fn dip721_resync_cap(from: Option<Nat>, limit: u32) -> Result<Nat, NftError> {
+    if STATUS.with(|status| status.borrow().flushing) {
+        return Err(NftError::Other(
+            "a cap flush is running, re-sync once it's over".into(),
+        ));
+    }
+    ledger::with_mut(|ledger| {
+        if ledger.history() != HistoryBackend::Both {
+            return Err(NftError::Other(
+                "re-syncing cap replays the tx log, it needs the `Both` history backend".into(),
+            ));
+        }
+        let tx_count = ledger.tx_count();
+        let first_logged_tx_id = ledger.first_logged_tx_id();
+        let (from, mut cursor) = match from {
+            Some(from) if from > tx_count => {
+                return Err(NftError::Other(
+                    "can't re-sync cap from a tx after `tx_count`".into(),
+                ))
+            }
+            Some(from) if from < first_logged_tx_id => {
+                return Err(NftError::Other(format!(
+                    "txs before {0} were recorded before the tx log and only cap holds them, \
+                     re-sync from {0} into a root bucket holding them",
+                    first_logged_tx_id
+                )))
+            }
+            Some(from) => {
+                ledger.set_cap_misaligned(None);
+                (from.clone(), from)
+            }
+            None => ledger
+                .cap_resync()
+                .ok_or_else(|| NftError::Other("no cap re-sync to resume".into()))?,
+        };
+        let limit = limit.min(MAX_RESYNC_SIZE) as usize;
+        // txs queued before `from` would reach cap twice, later ones are replayed over
+        let cleared = ledger.clear_cap_outbox(&from, limit);
+        for _ in cleared..limit {
+            if cursor >= tx_count {
+                break;
+            }
+            ledger.requeue_for_cap(&cursor)?;
+            cursor += 1;
+        }
+        if cleared < limit && cursor >= tx_count {
+            ledger.set_cap_resync(None);
+            schedule();
+        } else {
+            ledger.set_cap_resync(Some((from, cursor.clone())));
+        }
+        Ok(cursor)
+    })
+}
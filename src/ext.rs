@@ -0,0 +1,268 @@
+//! EXT compatibility, see https://github.com/Toniq-Labs/extendable-token
+//! Token identifiers are text encoded (canister id + index) and owners are account identifiers,
+//! both computed from the dip721 ledger.
+
+use crate::*;
+use num_traits::ToPrimitive;
+use std::convert::TryInto;
+
+const TOKEN_IDENTIFIER_DOMAIN: &[u8] = b"\x0Atid"; // followed by the canister id and the index
+
+pub type AccountIdentifier = String;
+pub type ExtTokenIdentifier = String;
+pub type TokenIndex = u32;
+pub type TokenListing = (TokenIndex, Option<Listing>, Option<Vec<u8>>); // (index, listing, metadata)
+
+#[derive(CandidType, Deserialize)]
+pub enum User {
+    #[serde(rename = "address")]
+    Address(AccountIdentifier),
+    #[serde(rename = "principal")]
+    Principal(Principal),
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum CommonError {
+    InvalidToken(ExtTokenIdentifier),
+    Other(String),
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum ExtResult<T> {
+    #[serde(rename = "ok")]
+    Ok(T),
+    #[serde(rename = "err")]
+    Err(CommonError),
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct Listing {
+    pub locked: Option<u64>,
+    pub seller: Principal,
+    pub price: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ExtTransferRequest {
+    pub from: User,
+    pub to: User,
+    pub token: ExtTokenIdentifier,
+    pub amount: Nat,
+    pub memo: Vec<u8>,
+    pub notify: bool,
+    pub subaccount: Option<Subaccount>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum ExtTransferError {
+    Unauthorized(AccountIdentifier),
+    InsufficientBalance,
+    Rejected,
+    InvalidToken(ExtTokenIdentifier),
+    CannotNotify(AccountIdentifier),
+    Other(String),
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum ExtTransferResponse {
+    #[serde(rename = "ok")]
+    Ok(Nat),
+    #[serde(rename = "err")]
+    Err(ExtTransferError),
+}
+
+/// Hex of crc32 + `Account::hash`.
+pub fn account_identifier(account: &Account) -> AccountIdentifier {
+    let hash = account.hash();
+    let mut account_identifier = crc32fast::hash(&hash).to_be_bytes().to_vec();
+    account_identifier.extend_from_slice(&hash);
+    hex::encode(account_identifier)
+}
+
+/// Index of a text token identifier issued by this canister.
+pub fn token_index(token_identifier: &str) -> Option<TokenIndex> {
+    let principal = Principal::from_text(token_identifier).ok()?;
+    let bytes = principal
+        .as_slice()
+        .strip_prefix(TOKEN_IDENTIFIER_DOMAIN)?
+        .strip_prefix(ic_cdk::id().as_slice())?;
+    Some(TokenIndex::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/// Account identifiers can't be reversed, only the ones of accounts that received an nft are known.
+fn holder_of(ledger: &ledger::Ledger, account_identifier: &str) -> Result<Account, String> {
+    let bytes = hex::decode(account_identifier)
+        .ok()
+        .filter(|bytes| bytes.len() == 32)
+        .ok_or_else(|| "invalid account identifier".to_string())?;
+    let (checksum, hash) = bytes.split_at(4);
+    if checksum != crc32fast::hash(hash).to_be_bytes() {
+        return Err("invalid account identifier checksum".into());
+    }
+    ledger
+        .account_of_hash(hash)
+        .ok_or_else(|| "unknown account identifier, transfer to the principal instead".into())
+}
+
+fn user_account(ledger: &ledger::Ledger, user: User) -> Result<Account, String> {
+    match user {
+        User::Principal(principal) => Ok(principal.into()),
+        User::Address(account_identifier) => holder_of(ledger, &account_identifier),
+    }
+}
+
+// ==================================================================================================
+// ext
+// ==================================================================================================
+#[query()]
+#[candid_method(query)]
+fn extensions() -> Vec<String> {
+    vec!["@ext/common".into(), "@ext/nonfungible".into()]
+}
+
+#[query()]
+#[candid_method(query)]
+fn bearer(token: ExtTokenIdentifier) -> ExtResult<AccountIdentifier> {
+    ledger::with(|ledger| {
+        match token_index(&token)
+            .and_then(|token_index| ledger.owner_account_of(&Nat::from(token_index)).ok())
+            .flatten()
+        {
+            Some(owner) => ExtResult::Ok(account_identifier(&owner)),
+            None => ExtResult::Err(CommonError::InvalidToken(token)),
+        }
+    })
+}
+
+#[query(name = "getRegistry")]
+#[candid_method(query, rename = "getRegistry")]
+fn get_registry() -> Vec<(TokenIndex, AccountIdentifier)> {
+    ledger::with(|ledger| {
+        ledger
+            .holders()
+            .into_iter()
+            .flat_map(|account| {
+                let account_identifier = account_identifier(&account);
+                ledger
+                    .account_token_identifiers(&account)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(move |token_identifier| {
+                        token_identifier
+                            .0
+                            .to_u32()
+                            .map(|token_index| (token_index, account_identifier.clone()))
+                    })
+            })
+            .collect()
+    })
+}
+
+/// Nfts aren't listed by this canister, listing and metadata are always empty.
+#[query()]
+#[candid_method(query)]
+fn tokens_ext(account_identifier: AccountIdentifier) -> ExtResult<Vec<TokenListing>> {
+    ledger::with(|ledger| {
+        let account = match holder_of(ledger, &account_identifier) {
+            Ok(account) => account,
+            Err(message) => return ExtResult::Err(CommonError::Other(message)),
+        };
+        let mut token_indexes: Vec<_> = ledger
+            .account_token_identifiers(&account)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|token_identifier| token_identifier.0.to_u32())
+            .collect();
+        if token_indexes.is_empty() {
+            return ExtResult::Err(CommonError::Other("No tokens".into()));
+        }
+        token_indexes.sort_unstable();
+        ExtResult::Ok(
+            token_indexes
+                .into_iter()
+                .map(|token_index| (token_index, None, None))
+                .collect(),
+        )
+    })
+}
+
+/// Recipients given as account identifiers must have received an nft from this canister before, unknown or
+/// malformed ones are rejected with `Other`. Self transfers are `Rejected`, notifications aren't supported.
+#[update]
+#[candid_method(update)]
+fn transfer(request: ExtTransferRequest) -> ExtTransferResponse {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let token_identifier = match token_index(&request.token) {
+            Some(token_index) => Nat::from(token_index),
+            None => return ExtTransferResponse::Err(ExtTransferError::InvalidToken(request.token)),
+        };
+        if request.amount != 1u8 {
+            return ExtTransferResponse::Err(ExtTransferError::Other("amount must be 1".into()));
+        }
+        let from = match (Account {
+            owner: caller,
+            subaccount: request.subaccount,
+        })
+        .normalized()
+        {
+            Ok(from) => from,
+            Err(_) => {
+                return ExtTransferResponse::Err(ExtTransferError::Other(
+                    "invalid subaccount".into(),
+                ))
+            }
+        };
+        let from_account_identifier = account_identifier(&from);
+        let is_from_caller = match &request.from {
+            User::Principal(principal) => principal.eq(&caller),
+            User::Address(account_identifier) => account_identifier.eq(&from_account_identifier),
+        };
+        if !is_from_caller {
+            return ExtTransferResponse::Err(ExtTransferError::Unauthorized(
+                from_account_identifier,
+            ));
+        }
+        match ledger.owner_account_of(&token_identifier) {
+            Ok(Some(owner)) if owner == from => {}
+            Ok(Some(_)) => {
+                return ExtTransferResponse::Err(ExtTransferError::Unauthorized(
+                    from_account_identifier,
+                ))
+            }
+            Ok(None) | Err(_) => {
+                return ExtTransferResponse::Err(ExtTransferError::InvalidToken(request.token))
+            }
+        }
+        let to = match user_account(ledger, request.to) {
+            Ok(to) if to != from => to,
+            Ok(_) => return ExtTransferResponse::Err(ExtTransferError::Rejected),
+            Err(message) => return ExtTransferResponse::Err(ExtTransferError::Other(message)),
+        };
+        if request.notify {
+            return ExtTransferResponse::Err(ExtTransferError::CannotNotify(account_identifier(
+                &to,
+            )));
+        }
+
+        let mut details = vec![];
+        if !request.memo.is_empty() {
+            details.push(("memo".into(), GenericValue::BlobContent(request.memo)));
+        }
+        match transfer_between_accounts(
+            ledger,
+            caller,
+            "transfer",
+            from,
+            to,
+            token_identifier,
+            details,
+        ) {
+            Ok(_) => ExtTransferResponse::Ok(request.amount),
+            Err(NftError::Other(message)) => {
+                ExtTransferResponse::Err(ExtTransferError::Other(message))
+            }
+            Err(_) => ExtTransferResponse::Err(ExtTransferError::Rejected),
+        }
+    })
+}
@@ -0,0 +1,99 @@
+//! Callbacks run by the `Ledger` on every mint, transfer and burn, whichever endpoint triggered it.
+//!
+//! `before_*` run before anything changes and veto the operation by returning an error,
+//! `after_*` run once the nft metadata is updated, the owner caches and the tx are recorded next.
+//! Hooks aren't persisted, canisters register them with [`set`] from their own `init` and
+//! `post_upgrade`. Such canisters are built without the `lifecycle` feature and export every lifecycle
+//! method themselves, each calling the library one:
+//! - `init` and `post_upgrade` call [`crate::init`] and [`crate::post_upgrade`], then register the hooks
+//! - `pre_upgrade` calls [`crate::pre_upgrade`], without it the metadata, the tx count and a cap
+//!   root bucket being installed are lost on upgrade
+//! - `canister_global_timer` calls [`crate::global_timer`], without it expired approvals stay in storage
+//!   and events never reach subscribers or cap
+//!
+//! See `examples/hooked.rs`.
+//!
+//! The ledger is borrowed during the callbacks, hooks read it through the `ledger` argument only.
+
+use crate::*;
+use ledger::Ledger;
+
+#[allow(unused_variables)]
+pub trait Hooks {
+    fn before_mint(
+        &self,
+        ledger: &Ledger,
+        caller: Principal,
+        to: &Principal,
+        token_identifier: &TokenIdentifier,
+        properties: &[(String, GenericValue)],
+    ) -> Result<(), NftError> {
+        Ok(())
+    }
+
+    fn after_mint(
+        &self,
+        ledger: &Ledger,
+        caller: Principal,
+        to: &Principal,
+        token_identifier: &TokenIdentifier,
+    ) {
+    }
+
+    fn before_transfer(
+        &self,
+        ledger: &Ledger,
+        caller: Principal,
+        from: &Account,
+        to: &Account,
+        token_identifier: &TokenIdentifier,
+    ) -> Result<(), NftError> {
+        Ok(())
+    }
+
+    fn after_transfer(
+        &self,
+        ledger: &Ledger,
+        caller: Principal,
+        from: &Account,
+        to: &Account,
+        token_identifier: &TokenIdentifier,
+    ) {
+    }
+
+    fn before_burn(
+        &self,
+        ledger: &Ledger,
+        caller: Principal,
+        owner: &Account,
+        token_identifier: &TokenIdentifier,
+    ) -> Result<(), NftError> {
+        Ok(())
+    }
+
+    fn after_burn(
+        &self,
+        ledger: &Ledger,
+        caller: Principal,
+        owner: &Account,
+        token_identifier: &TokenIdentifier,
+    ) {
+    }
+}
+
+struct NoHooks;
+
+impl Hooks for NoHooks {}
+
+thread_local!(
+    static HOOKS: RefCell<Box<dyn Hooks>> = RefCell::new(Box::new(NoHooks));
+);
+
+/// Replaces the registered hooks.
+pub fn set(hooks: impl Hooks + 'static) {
+    HOOKS.with(|cell| *cell.borrow_mut() = Box::new(hooks));
+}
+
+pub(crate) fn with<T, F: FnOnce(&dyn Hooks) -> T>(f: F) -> T {
+    HOOKS.with(|hooks| f(hooks.borrow().as_ref()))
+}
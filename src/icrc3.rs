@@ -0,0 +1,304 @@
+use crate::icrc7::{ICRC37_URL, ICRC7_URL};
+use crate::*;
+use ic_cdk::api::{data_certificate, set_certified_data};
+use ic_cdk::export::candid::parser::types::FuncMode;
+use ic_cdk::export::candid::types::{Function, Serializer, Type};
+use ic_cdk::export::candid::Func;
+use ic_certified_map::{fork, labeled, HashTree};
+use num_traits::ToPrimitive;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+const MAX_BLOCKS_PER_RESPONSE: u64 = 100;
+
+pub type Hash = [u8; 32];
+
+#[derive(CandidType, Deserialize)]
+pub struct GetBlocksArgs {
+    pub start: Nat,
+    pub length: Nat,
+}
+
+#[derive(CandidType)]
+pub struct BlockWithId {
+    pub id: Nat,
+    pub block: Value,
+}
+
+/// Blocks are never archived, the callback only completes the ICRC-3 signature.
+pub struct GetBlocksCallback(pub Func);
+
+impl CandidType for GetBlocksCallback {
+    fn _ty() -> Type {
+        Type::Func(Function {
+            modes: vec![FuncMode::Query],
+            args: vec![Vec::<GetBlocksArgs>::ty()],
+            rets: vec![GetBlocksResult::ty()],
+        })
+    }
+
+    fn idl_serialize<S: Serializer>(&self, serializer: S) -> Result<(), S::Error> {
+        self.0.idl_serialize(serializer)
+    }
+}
+
+#[derive(CandidType)]
+pub struct ArchivedBlocks {
+    pub args: Vec<GetBlocksArgs>,
+    pub callback: GetBlocksCallback,
+}
+
+#[derive(CandidType)]
+pub struct GetBlocksResult {
+    pub log_length: Nat,
+    pub blocks: Vec<BlockWithId>,
+    pub archived_blocks: Vec<ArchivedBlocks>,
+}
+
+#[derive(CandidType)]
+pub struct DataCertificate {
+    pub certificate: Vec<u8>,
+    pub hash_tree: Vec<u8>,
+}
+
+#[derive(CandidType)]
+pub struct SupportedBlockType {
+    pub block_type: String,
+    pub url: String,
+}
+
+fn sha256(data: &[u8]) -> Hash {
+    Sha256::digest(data).into()
+}
+
+fn leb128(nat: &Nat) -> Vec<u8> {
+    let mut bytes = vec![];
+    nat.encode(&mut bytes).expect("couldn't encode nat");
+    bytes
+}
+
+/// Representation independent hash of `value`, as specified by ICRC-3.
+pub fn hash_value(value: &Value) -> Hash {
+    match value {
+        Value::Blob(blob) => sha256(blob),
+        Value::Text(text) => sha256(text.as_bytes()),
+        Value::Nat(nat) => sha256(&leb128(nat)),
+        Value::Int(int) => {
+            let mut bytes = vec![];
+            int.encode(&mut bytes).expect("couldn't encode int");
+            sha256(&bytes)
+        }
+        Value::Array(values) => sha256(
+            &values
+                .iter()
+                .flat_map(|value| hash_value(value).to_vec())
+                .collect::<Vec<_>>(),
+        ),
+        Value::Map(entries) => {
+            let mut entries: Vec<_> = entries
+                .iter()
+                .map(|(key, value)| [sha256(key.as_bytes()), hash_value(value)].concat())
+                .collect();
+            entries.sort();
+            sha256(&entries.concat())
+        }
+    }
+}
+
+fn block_type(operation: &str, details: &[(String, GenericValue)]) -> String {
+    let is_revoked = details.iter().any(
+        |detail| matches!(detail, (key, GenericValue::BoolContent(false)) if key == "is_approved"),
+    );
+    match operation {
+        "mint" => "7mint",
+        "burn" => "7burn",
+        "transfer" => "7xfer",
+        "transferFrom" => "37xfer",
+        "approve" => "37approve",
+        "revoke" => "37revoke",
+        "setApprovalForAll" if is_revoked => "37revoke_coll",
+        "setApprovalForAll" => "37approve_coll",
+        operation => operation,
+    }
+    .into()
+}
+
+fn account_value(owner: Principal, subaccount: Option<Subaccount>) -> Value {
+    let mut account = vec![Value::Blob(owner.as_slice().to_vec())];
+    account.extend(subaccount.map(Value::Blob));
+    Value::Array(account)
+}
+
+/// Tx fields named after the ICRC-7 and ICRC-37 block schemas, other details are kept as is.
+fn block_tx(
+    caller: Principal,
+    operation: &str,
+    details: &[(String, GenericValue)],
+) -> Vec<(String, Value)> {
+    let subaccount_of = |account: &str| {
+        details.iter().find_map(|(key, value)| match value {
+            GenericValue::BlobContent(subaccount) if key.eq(&format!("{}_subaccount", account)) => {
+                Some(subaccount.clone())
+            }
+            _ => None,
+        })
+    };
+
+    let mut tx = vec![];
+    if !details.iter().any(|(key, _)| key == "owner") {
+        tx.push(("from".into(), account_value(caller, None)));
+    }
+    if operation == "transferFrom" {
+        tx.push(("spender".into(), account_value(caller, None)));
+    }
+    for (key, value) in details {
+        match (key.as_str(), value) {
+            ("token_identifier", value) => tx.push(("tid".into(), value.clone().into())),
+            (account @ ("owner" | "to" | "operator"), GenericValue::Principal(principal)) => {
+                let field = match account {
+                    "owner" => "from",
+                    "operator" => "spender",
+                    _ => "to",
+                };
+                tx.push((
+                    field.into(),
+                    account_value(*principal, subaccount_of(account)),
+                ));
+            }
+            ("owner_subaccount" | "to_subaccount", _) => {}
+            ("created_at_time", value) => tx.push(("ts".into(), value.clone().into())),
+            ("expires_at", value) => tx.push(("exp".into(), value.clone().into())),
+            (key, value) => tx.push((key.into(), value.clone().into())),
+        }
+    }
+    tx
+}
+
+/// Block for a tx, `phash` is the hash of the previous block.
+pub fn block(
+    phash: Option<Hash>,
+    timestamp: u64,
+    caller: Principal,
+    operation: &str,
+    details: &[(String, GenericValue)],
+) -> Value {
+    let mut block = vec![];
+    if let Some(phash) = phash {
+        block.push(("phash".into(), Value::Blob(phash.to_vec())));
+    }
+    block.push(("btype".into(), Value::Text(block_type(operation, details))));
+    block.push(("ts".into(), Value::Nat(Nat::from(timestamp))));
+    block.push((
+        "tx".into(),
+        Value::Map(block_tx(caller, operation, details)),
+    ));
+    Value::Map(block)
+}
+
+fn tip_tree<'a>(index: &'a [u8], hash: &'a [u8]) -> HashTree<'a> {
+    fork(
+        labeled(b"last_block_hash", HashTree::Leaf(hash)),
+        labeled(b"last_block_index", HashTree::Leaf(index)),
+    )
+}
+
+/// Sets the certified data to the root of the tip hash tree.
+pub fn certify(tip: Option<(u64, Hash)>) {
+    if let Some((index, hash)) = tip {
+        let index = leb128(&Nat::from(index));
+        set_certified_data(&tip_tree(&index, &hash).reconstruct());
+    }
+}
+
+/// Blocks are only recorded with the tx log, the endpoints reject the calls when it's disabled.
+fn check_log_enabled() {
+    if !ledger::with(|ledger| ledger.history().logs()) {
+        trap("the ICRC-3 log is disabled by the history backend");
+    }
+}
+
+// ==================================================================================================
+// block log
+// ==================================================================================================
+#[query()]
+#[candid_method(query)]
+fn icrc3_get_blocks(args: Vec<GetBlocksArgs>) -> GetBlocksResult {
+    check_log_enabled();
+    ledger::with(|ledger| {
+        let log_length = ledger.blocks_count();
+        let mut blocks = vec![];
+        for arg in args {
+            let start = arg.start.0.to_u64().unwrap_or(u64::MAX);
+            let length = arg
+                .length
+                .0
+                .to_u64()
+                .unwrap_or(u64::MAX)
+                .min(MAX_BLOCKS_PER_RESPONSE - blocks.len() as u64);
+            for index in start..start.saturating_add(length).min(log_length) {
+                if let Some(block) = ledger.block(index) {
+                    blocks.push(BlockWithId {
+                        id: Nat::from(index),
+                        block,
+                    });
+                }
+            }
+        }
+        GetBlocksResult {
+            log_length: Nat::from(log_length),
+            blocks,
+            archived_blocks: vec![],
+        }
+    })
+}
+
+/// Only available in non-replicated queries, `None` before the first block.
+#[query()]
+#[candid_method(query)]
+fn icrc3_get_tip_certificate() -> Option<DataCertificate> {
+    check_log_enabled();
+    let certificate = data_certificate()?;
+    ledger::with(|ledger| {
+        let (index, hash) = ledger.tip()?;
+        let index = leb128(&Nat::from(index));
+        let mut serializer = serde_cbor::Serializer::new(vec![]);
+        serializer.self_describe().expect("couldn't write cbor tag");
+        tip_tree(&index, &hash)
+            .serialize(&mut serializer)
+            .expect("couldn't serialize hash tree");
+        Some(DataCertificate {
+            certificate,
+            hash_tree: serializer.into_inner(),
+        })
+    })
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc3_supported_block_types() -> Vec<SupportedBlockType> {
+    check_log_enabled();
+    let mut block_types = vec![];
+    if cfg!(feature = "mint") {
+        block_types.push(("7mint", ICRC7_URL));
+    }
+    if cfg!(feature = "burn") {
+        block_types.push(("7burn", ICRC7_URL));
+    }
+    block_types.push(("7xfer", ICRC7_URL));
+    if cfg!(feature = "approval") {
+        block_types.extend([
+            ("37approve", ICRC37_URL),
+            ("37approve_coll", ICRC37_URL),
+            ("37revoke", ICRC37_URL),
+            ("37revoke_coll", ICRC37_URL),
+            ("37xfer", ICRC37_URL),
+        ]);
+    }
+    block_types
+        .iter()
+        .map(|(block_type, url)| SupportedBlockType {
+            block_type: block_type.to_string(),
+            url: url.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,551 @@
+use crate::icrc7::*;
+use crate::*;
+use ic_cdk::export::candid::encode_one;
+
+#[derive(CandidType, Deserialize, Clone)]
+pub struct ApprovalInfo {
+    pub spender: Account,
+    pub from_subaccount: Option<Subaccount>,
+    pub expires_at: Option<u64>,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ApproveTokenArg {
+    pub token_id: TokenIdentifier,
+    pub approval_info: ApprovalInfo,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum ApproveTokenError {
+    InvalidSpender,
+    Unauthorized,
+    NonExistingTokenId,
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    GenericError { error_code: Nat, message: String },
+    GenericBatchError { error_code: Nat, message: String },
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ApproveCollectionArg {
+    pub approval_info: ApprovalInfo,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum ApproveCollectionError {
+    InvalidSpender,
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    GenericError { error_code: Nat, message: String },
+    GenericBatchError { error_code: Nat, message: String },
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct RevokeTokenApprovalArg {
+    pub spender: Option<Account>, // `None` revokes every spender
+    pub from_subaccount: Option<Subaccount>,
+    pub token_id: TokenIdentifier,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum RevokeTokenApprovalError {
+    ApprovalDoesNotExist,
+    Unauthorized,
+    NonExistingTokenId,
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    GenericError { error_code: Nat, message: String },
+    GenericBatchError { error_code: Nat, message: String },
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct RevokeCollectionApprovalArg {
+    pub spender: Option<Account>, // `None` revokes every spender
+    pub from_subaccount: Option<Subaccount>,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum RevokeCollectionApprovalError {
+    ApprovalDoesNotExist,
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    GenericError { error_code: Nat, message: String },
+    GenericBatchError { error_code: Nat, message: String },
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct IsApprovedArg {
+    pub spender: Account,
+    pub from_subaccount: Option<Subaccount>,
+    pub token_id: TokenIdentifier,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct TokenApproval {
+    pub token_id: TokenIdentifier,
+    pub approval_info: ApprovalInfo,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct TransferFromArg {
+    pub spender_subaccount: Option<Subaccount>,
+    pub from: Account,
+    pub to: Account,
+    pub token_id: TokenIdentifier,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum TransferFromError {
+    InvalidRecipient,
+    Unauthorized,
+    NonExistingTokenId,
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    Duplicate { duplicate_of: Nat },
+    GenericError { error_code: Nat, message: String },
+    GenericBatchError { error_code: Nat, message: String },
+}
+
+impl_icrc_error!(
+    ApproveTokenError,
+    ApproveCollectionError,
+    RevokeTokenApprovalError,
+    RevokeCollectionApprovalError,
+    TransferFromError
+);
+
+/// Operators are principals, so only default subaccounts can be approved as spenders.
+fn spender_principal(spender: Account, caller: Principal) -> Option<Principal> {
+    spender
+        .normalized()
+        .ok()
+        .filter(|spender| spender.subaccount.is_none() && spender.owner.ne(&caller))
+        .map(|spender| spender.owner)
+}
+
+/// Approvals for all are kept per principal, they only cover the default subaccount.
+fn is_default_subaccount(subaccount: &Option<Subaccount>) -> bool {
+    subaccount
+        .as_ref()
+        .is_none_or(|subaccount| subaccount.eq(&[0; 32]))
+}
+
+fn approval_details(
+    operator: Principal,
+    expires_at: Option<u64>,
+    memo: Option<Vec<u8>>,
+    created_at_time: Option<u64>,
+) -> Vec<(String, GenericValue)> {
+    let mut details = vec![("operator".into(), GenericValue::Principal(operator))];
+    if let Some(expires_at) = expires_at {
+        details.push(("expires_at".into(), GenericValue::Nat64Content(expires_at)));
+    }
+    details.extend(icrc_details(memo, created_at_time));
+    details
+}
+
+// ==================================================================================================
+// approvals
+// ==================================================================================================
+/// Shares the token operators with `dip721_approve`.
+#[update]
+#[candid_method(update)]
+fn icrc37_approve_tokens(
+    args: Vec<ApproveTokenArg>,
+) -> Vec<Option<Result<Nat, ApproveTokenError>>> {
+    if let Some(reply) = oversized_update_batch(&args) {
+        return reply;
+    }
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let now = time();
+        args.into_iter()
+            .map(|arg| Some(approve_token(ledger, caller, now, arg)))
+            .collect()
+    })
+}
+
+fn approve_token(
+    ledger: &mut ledger::Ledger,
+    caller: Principal,
+    now: u64,
+    arg: ApproveTokenArg,
+) -> Result<Nat, ApproveTokenError> {
+    let approval_info = arg.approval_info;
+    check_memo(&approval_info.memo)?;
+    check_created_at_time(approval_info.created_at_time, now)?;
+    let operator = spender_principal(approval_info.spender, caller)
+        .ok_or(ApproveTokenError::InvalidSpender)?;
+    is_valid_expiry(approval_info.expires_at)
+        .map_err(|_| ApproveTokenError::generic_error("expires_at is in the past".into()))?;
+    let from = Account {
+        owner: caller,
+        subaccount: approval_info.from_subaccount,
+    }
+    .normalized()
+    .map_err(|_| ApproveTokenError::Unauthorized)?;
+    match ledger.owner_account_of(&arg.token_id) {
+        Ok(Some(owner)) if owner == from => {}
+        Ok(Some(_)) => return Err(ApproveTokenError::Unauthorized),
+        Ok(None) | Err(_) => return Err(ApproveTokenError::NonExistingTokenId),
+    }
+
+    ledger.update_operator_cache(&arg.token_id, None, Some(operator));
+    ledger.approve(caller, &arg.token_id, operator, approval_info.expires_at);
+
+    let mut details = approval_details(
+        operator,
+        approval_info.expires_at,
+        approval_info.memo,
+        Some(approval_info.created_at_time),
+    );
+    details.insert(
+        1,
+        (
+            "token_identifier".into(),
+            GenericValue::NatContent(arg.token_id),
+        ),
+    );
+    Ok(ledger.add_tx(caller, "approve".into(), details))
+}
+
+/// Shares the approvals for all with `dip721_set_approval_for_all`.
+#[update]
+#[candid_method(update)]
+fn icrc37_approve_collection(
+    args: Vec<ApproveCollectionArg>,
+) -> Vec<Option<Result<Nat, ApproveCollectionError>>> {
+    if let Some(reply) = oversized_update_batch(&args) {
+        return reply;
+    }
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let now = time();
+        args.into_iter()
+            .map(|arg| Some(approve_collection(ledger, caller, now, arg)))
+            .collect()
+    })
+}
+
+fn approve_collection(
+    ledger: &mut ledger::Ledger,
+    caller: Principal,
+    now: u64,
+    arg: ApproveCollectionArg,
+) -> Result<Nat, ApproveCollectionError> {
+    let approval_info = arg.approval_info;
+    check_memo(&approval_info.memo)?;
+    check_created_at_time(approval_info.created_at_time, now)?;
+    let operator = spender_principal(approval_info.spender, caller)
+        .ok_or(ApproveCollectionError::InvalidSpender)?;
+    is_valid_expiry(approval_info.expires_at)
+        .map_err(|_| ApproveCollectionError::generic_error("expires_at is in the past".into()))?;
+    if !is_default_subaccount(&approval_info.from_subaccount) {
+        return Err(ApproveCollectionError::generic_error(
+            "collection approvals are only supported from the default subaccount".into(),
+        ));
+    }
+
+    ledger.set_approval_for_all(caller, operator, true, approval_info.expires_at);
+
+    let mut details = approval_details(
+        operator,
+        approval_info.expires_at,
+        approval_info.memo,
+        Some(approval_info.created_at_time),
+    );
+    details.insert(1, ("is_approved".into(), GenericValue::BoolContent(true)));
+    Ok(ledger.add_tx(caller, "setApprovalForAll".into(), details))
+}
+
+/// Every revoked spender gets its own tx, the last one is returned.
+#[update]
+#[candid_method(update)]
+fn icrc37_revoke_token_approvals(
+    args: Vec<RevokeTokenApprovalArg>,
+) -> Vec<Option<Result<Nat, RevokeTokenApprovalError>>> {
+    if let Some(reply) = oversized_update_batch(&args) {
+        return reply;
+    }
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let now = time();
+        args.into_iter()
+            .map(|arg| Some(revoke_token_approvals(ledger, caller, now, arg)))
+            .collect()
+    })
+}
+
+fn revoke_token_approvals(
+    ledger: &mut ledger::Ledger,
+    caller: Principal,
+    now: u64,
+    arg: RevokeTokenApprovalArg,
+) -> Result<Nat, RevokeTokenApprovalError> {
+    check_memo(&arg.memo)?;
+    if let Some(created_at_time) = arg.created_at_time {
+        check_created_at_time(created_at_time, now)?;
+    }
+    let from = Account {
+        owner: caller,
+        subaccount: arg.from_subaccount,
+    }
+    .normalized()
+    .map_err(|_| RevokeTokenApprovalError::Unauthorized)?;
+    match ledger.owner_account_of(&arg.token_id) {
+        Ok(Some(owner)) if owner == from => {}
+        Ok(Some(_)) => return Err(RevokeTokenApprovalError::Unauthorized),
+        Ok(None) | Err(_) => return Err(RevokeTokenApprovalError::NonExistingTokenId),
+    }
+    let operators = ledger.operators_of(&arg.token_id).unwrap_or_default();
+    let revoked: Vec<_> = match arg.spender {
+        Some(spender) => spender_principal(spender, caller)
+            .filter(|operator| operators.contains(operator))
+            .into_iter()
+            .collect(),
+        None => operators,
+    };
+    if revoked.is_empty() {
+        return Err(RevokeTokenApprovalError::ApprovalDoesNotExist);
+    }
+
+    let mut tx_id = Nat::from(0u8);
+    for operator in revoked {
+        ledger.update_operator_cache(&arg.token_id, Some(operator), None);
+        ledger.revoke(caller, &arg.token_id, &operator);
+
+        let mut details = approval_details(operator, None, arg.memo.clone(), arg.created_at_time);
+        details.insert(
+            1,
+            (
+                "token_identifier".into(),
+                GenericValue::NatContent(arg.token_id.clone()),
+            ),
+        );
+        tx_id = ledger.add_tx(caller, "revoke".into(), details);
+    }
+    Ok(tx_id)
+}
+
+/// Every revoked spender gets its own tx, the last one is returned.
+#[update]
+#[candid_method(update)]
+fn icrc37_revoke_collection_approvals(
+    args: Vec<RevokeCollectionApprovalArg>,
+) -> Vec<Option<Result<Nat, RevokeCollectionApprovalError>>> {
+    if let Some(reply) = oversized_update_batch(&args) {
+        return reply;
+    }
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let now = time();
+        args.into_iter()
+            .map(|arg| Some(revoke_collection_approvals(ledger, caller, now, arg)))
+            .collect()
+    })
+}
+
+fn revoke_collection_approvals(
+    ledger: &mut ledger::Ledger,
+    caller: Principal,
+    now: u64,
+    arg: RevokeCollectionApprovalArg,
+) -> Result<Nat, RevokeCollectionApprovalError> {
+    check_memo(&arg.memo)?;
+    if let Some(created_at_time) = arg.created_at_time {
+        check_created_at_time(created_at_time, now)?;
+    }
+    if !is_default_subaccount(&arg.from_subaccount) {
+        return Err(RevokeCollectionApprovalError::ApprovalDoesNotExist);
+    }
+    let revoked: Vec<_> = match arg.spender {
+        Some(spender) => spender_principal(spender, caller)
+            .filter(|operator| ledger.is_approved_for_all(&caller, operator))
+            .into_iter()
+            .collect(),
+        None => ledger
+            .approvals_for_all_of(&caller)
+            .into_iter()
+            .filter(|operator| ledger.is_approved_for_all(&caller, operator))
+            .collect(),
+    };
+    if revoked.is_empty() {
+        return Err(RevokeCollectionApprovalError::ApprovalDoesNotExist);
+    }
+
+    let mut tx_id = Nat::from(0u8);
+    for operator in revoked {
+        ledger.set_approval_for_all(caller, operator, false, None);
+
+        let mut details = approval_details(operator, None, arg.memo.clone(), arg.created_at_time);
+        details.insert(1, ("is_approved".into(), GenericValue::BoolContent(false)));
+        tx_id = ledger.add_tx(caller, "setApprovalForAll".into(), details);
+    }
+    Ok(tx_id)
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc37_is_approved(args: Vec<IsApprovedArg>) -> Vec<bool> {
+    check_query_batch_size(&args);
+    ledger::with(|ledger| {
+        args.into_iter()
+            .map(|arg| {
+                let owner = match ledger.owner_account_of(&arg.token_id) {
+                    Ok(Some(owner)) => owner,
+                    _ => return false,
+                };
+                let from = Account {
+                    owner: owner.owner,
+                    subaccount: arg.from_subaccount,
+                };
+                let operator = match spender_principal(arg.spender, owner.owner) {
+                    Some(operator) => operator,
+                    None => return false,
+                };
+                from.normalized().is_ok_and(|from| from == owner)
+                    && (ledger
+                        .operators_of(&arg.token_id)
+                        .is_ok_and(|operators| operators.contains(&operator))
+                        || owner.subaccount.is_none()
+                            && ledger.is_approved_for_all(&owner.owner, &operator))
+            })
+            .collect()
+    })
+}
+
+/// Live token approvals in approval order, starting after `prev`.
+/// Memo and created_at_time aren't kept per approval: `created_at_time` is the token's latest approval time.
+#[query()]
+#[candid_method(query)]
+fn icrc37_get_token_approvals(
+    token_id: TokenIdentifier,
+    prev: Option<TokenApproval>,
+    take: Option<Nat>,
+) -> Vec<TokenApproval> {
+    let take = take_value(take);
+    ledger::with(|ledger| {
+        let token_metadata = match ledger.token_metadata(&token_id) {
+            Ok(token_metadata) => token_metadata,
+            Err(_) => return vec![],
+        };
+        let skip = prev
+            .and_then(|prev| {
+                token_metadata
+                    .operators
+                    .iter()
+                    .position(|operator| operator.eq(&prev.approval_info.spender.owner))
+            })
+            .map_or(0, |position| position + 1);
+        token_metadata
+            .operators
+            .iter()
+            .skip(skip)
+            .take(take)
+            .map(|operator| TokenApproval {
+                token_id: token_id.clone(),
+                approval_info: ApprovalInfo {
+                    spender: (*operator).into(),
+                    from_subaccount: token_metadata.owner_subaccount.clone(),
+                    expires_at: ledger.approval_expires_at(&token_id, operator),
+                    memo: None,
+                    created_at_time: token_metadata.approved_at.unwrap_or_default(),
+                },
+            })
+            .collect()
+    })
+}
+
+// ==================================================================================================
+// transfer
+// ==================================================================================================
+/// Not atomic: every transfer gets its own result, in the same order as `args`.
+#[update]
+#[candid_method(update)]
+fn icrc37_transfer_from(args: Vec<TransferFromArg>) -> Vec<Option<Result<Nat, TransferFromError>>> {
+    if let Some(reply) = oversized_update_batch(&args) {
+        return reply;
+    }
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let now = time();
+        ledger.prune_recent_transfers(now.saturating_sub(TX_WINDOW + PERMITTED_DRIFT));
+        args.into_iter()
+            .map(|arg| Some(transfer_from(ledger, caller, now, arg)))
+            .collect()
+    })
+}
+
+fn transfer_from(
+    ledger: &mut ledger::Ledger,
+    caller: Principal,
+    now: u64,
+    arg: TransferFromArg,
+) -> Result<Nat, TransferFromError> {
+    check_memo(&arg.memo)?;
+    // only transfers with a `created_at_time` are deduplicated
+    let dedup = match arg.created_at_time {
+        Some(created_at_time) => {
+            check_created_at_time(created_at_time, now)?;
+            let encoded_arg = encode_one(&arg).expect("couldn't encode transfer_from arg");
+            if let Some(duplicate_of) =
+                ledger.recent_transfer(created_at_time, caller, encoded_arg.clone())
+            {
+                return Err(TransferFromError::Duplicate { duplicate_of });
+            }
+            Some((created_at_time, encoded_arg))
+        }
+        None => None,
+    };
+
+    if !is_default_subaccount(&arg.spender_subaccount) {
+        return Err(TransferFromError::Unauthorized);
+    }
+    let from = arg
+        .from
+        .normalized()
+        .map_err(|_| TransferFromError::Unauthorized)?;
+    let to = arg
+        .to
+        .normalized()
+        .map_err(|_| TransferFromError::InvalidRecipient)?;
+    if to == from {
+        return Err(TransferFromError::InvalidRecipient);
+    }
+    match ledger.owner_account_of(&arg.token_id) {
+        Ok(Some(owner)) if owner == from => {}
+        Ok(Some(_)) => return Err(TransferFromError::Unauthorized),
+        Ok(None) | Err(_) => return Err(TransferFromError::NonExistingTokenId),
+    }
+    let is_approved_for_all =
+        from.subaccount.is_none() && ledger.is_approved_for_all(&from.owner, &caller);
+    let is_operator = ledger
+        .operators_of(&arg.token_id)
+        .is_ok_and(|operators| operators.contains(&caller));
+    if !is_operator && !is_approved_for_all {
+        return Err(TransferFromError::Unauthorized);
+    }
+
+    let tx_id = transfer_between_accounts(
+        ledger,
+        caller,
+        "transferFrom",
+        from,
+        to,
+        arg.token_id,
+        icrc_details(arg.memo, arg.created_at_time),
+    )
+    .map_err(vetoed)?;
+    if let Some((created_at_time, encoded_arg)) = dedup {
+        ledger.add_recent_transfer(created_at_time, caller, encoded_arg, tx_id.clone());
+    }
+    Ok(tx_id)
+}
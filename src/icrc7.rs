@@ -0,0 +1,467 @@
+use crate::*;
+use ic_cdk::export::candid::encode_one;
+use num_traits::ToPrimitive;
+
+pub const MAX_QUERY_BATCH_SIZE: usize = 100;
+pub const MAX_UPDATE_BATCH_SIZE: usize = 20;
+pub const DEFAULT_TAKE_VALUE: usize = 100;
+pub const MAX_TAKE_VALUE: usize = 1000;
+pub const MAX_MEMO_SIZE: usize = 32;
+pub const TX_WINDOW: u64 = 24 * 60 * 60 * 1_000_000_000; // nanoseconds
+pub const PERMITTED_DRIFT: u64 = 2 * 60 * 1_000_000_000; // nanoseconds
+pub const ICRC3_URL: &str =
+    "https://github.com/dfinity/ICRC-1/blob/main/standards/ICRC-3/README.md";
+pub const ICRC7_URL: &str = "https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-7/ICRC-7.md";
+pub const ICRC37_URL: &str = "https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-37/ICRC-37.md";
+
+#[derive(CandidType, Deserialize)]
+pub struct TransferArg {
+    pub from_subaccount: Option<Subaccount>,
+    pub to: Account,
+    pub token_id: TokenIdentifier,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum TransferError {
+    NonExistingTokenId,
+    InvalidRecipient,
+    Unauthorized,
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    Duplicate { duplicate_of: Nat },
+    GenericError { error_code: Nat, message: String },
+    GenericBatchError { error_code: Nat, message: String },
+}
+
+pub type TransferResult = Result<Nat, TransferError>;
+
+/// Variants shared by the error types of the icrc update calls.
+pub trait IcrcError {
+    fn too_old() -> Self;
+    fn created_in_future(ledger_time: u64) -> Self;
+    fn generic_error(message: String) -> Self;
+    fn generic_batch_error(message: String) -> Self;
+}
+
+macro_rules! impl_icrc_error {
+    ($($error:ty),*) => {$(
+        impl IcrcError for $error {
+            fn too_old() -> Self {
+                Self::TooOld
+            }
+            fn created_in_future(ledger_time: u64) -> Self {
+                Self::CreatedInFuture { ledger_time }
+            }
+            fn generic_error(message: String) -> Self {
+                Self::GenericError { error_code: Nat::from(0u8), message }
+            }
+            fn generic_batch_error(message: String) -> Self {
+                Self::GenericBatchError { error_code: Nat::from(0u8), message }
+            }
+        }
+    )*};
+}
+#[cfg(feature = "approval")] // the ICRC-37 errors
+pub(crate) use impl_icrc_error;
+
+impl_icrc_error!(TransferError);
+
+#[derive(CandidType, Deserialize)]
+pub struct Standard {
+    pub name: String,
+    pub url: String,
+}
+
+pub fn check_query_batch_size<T>(batch: &[T]) {
+    if batch.len() > MAX_QUERY_BATCH_SIZE {
+        trap(&format!(
+            "batch exceeds max_query_batch_size of {}",
+            MAX_QUERY_BATCH_SIZE
+        ));
+    }
+}
+
+pub fn take_value(take: Option<Nat>) -> usize {
+    take.map_or(DEFAULT_TAKE_VALUE, |take| {
+        take.0.to_usize().unwrap_or(MAX_TAKE_VALUE)
+    })
+    .min(MAX_TAKE_VALUE)
+}
+
+/// The whole reply when `batch` is over `max_update_batch_size`.
+pub fn oversized_update_batch<T, E: IcrcError>(batch: &[T]) -> Option<Vec<Option<Result<Nat, E>>>> {
+    (batch.len() > MAX_UPDATE_BATCH_SIZE).then(|| {
+        vec![Some(Err(E::generic_batch_error(format!(
+            "batch exceeds max_update_batch_size of {}",
+            MAX_UPDATE_BATCH_SIZE
+        ))))]
+    })
+}
+
+pub fn check_memo<E: IcrcError>(memo: &Option<Vec<u8>>) -> Result<(), E> {
+    match memo {
+        Some(memo) if memo.len() > MAX_MEMO_SIZE => Err(E::generic_error(format!(
+            "memo exceeds max_memo_size of {}",
+            MAX_MEMO_SIZE
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Ledger errors left once the icrc checks passed, i.e. hook vetoes.
+pub fn vetoed<E: IcrcError>(error: NftError) -> E {
+    match error {
+        NftError::Other(message) => E::generic_error(message),
+        _ => E::generic_error("rejected by the ledger".into()),
+    }
+}
+
+pub fn check_created_at_time<E: IcrcError>(created_at_time: u64, now: u64) -> Result<(), E> {
+    if created_at_time < now.saturating_sub(TX_WINDOW + PERMITTED_DRIFT) {
+        return Err(E::too_old());
+    }
+    if created_at_time > now.saturating_add(PERMITTED_DRIFT) {
+        return Err(E::created_in_future(now));
+    }
+    Ok(())
+}
+
+/// Tx details for the optional memo and created_at_time of an icrc call.
+pub fn icrc_details(
+    memo: Option<Vec<u8>>,
+    created_at_time: Option<u64>,
+) -> Vec<(String, GenericValue)> {
+    let mut details = vec![];
+    if let Some(memo) = memo {
+        details.push(("memo".into(), GenericValue::BlobContent(memo)));
+    }
+    if let Some(created_at_time) = created_at_time {
+        details.push((
+            "created_at_time".into(),
+            GenericValue::Nat64Content(created_at_time),
+        ));
+    }
+    details
+}
+
+// ==================================================================================================
+// metadata
+// ==================================================================================================
+#[query()]
+#[candid_method(query)]
+fn icrc7_collection_metadata() -> Vec<(String, Value)> {
+    let mut metadata = vec![
+        ("icrc7:name".into(), Value::Text(icrc7_name())),
+        ("icrc7:symbol".into(), Value::Text(icrc7_symbol())),
+        (
+            "icrc7:total_supply".into(),
+            Value::Nat(icrc7_total_supply()),
+        ),
+    ];
+    if let Some(logo) = icrc7_logo() {
+        metadata.push(("icrc7:logo".into(), Value::Text(logo)));
+    }
+    if let Some(supply_cap) = icrc7_supply_cap() {
+        metadata.push(("icrc7:supply_cap".into(), Value::Nat(supply_cap)));
+    }
+    metadata.extend([
+        (
+            "icrc7:max_query_batch_size".into(),
+            Value::Nat(Nat::from(MAX_QUERY_BATCH_SIZE)),
+        ),
+        (
+            "icrc7:max_update_batch_size".into(),
+            Value::Nat(Nat::from(MAX_UPDATE_BATCH_SIZE)),
+        ),
+        (
+            "icrc7:default_take_value".into(),
+            Value::Nat(Nat::from(DEFAULT_TAKE_VALUE)),
+        ),
+        (
+            "icrc7:max_take_value".into(),
+            Value::Nat(Nat::from(MAX_TAKE_VALUE)),
+        ),
+        (
+            "icrc7:max_memo_size".into(),
+            Value::Nat(Nat::from(MAX_MEMO_SIZE)),
+        ),
+        (
+            "icrc7:atomic_batch_transfers".into(),
+            Value::Text("false".into()),
+        ),
+        ("icrc7:tx_window".into(), Value::Nat(Nat::from(TX_WINDOW))),
+        (
+            "icrc7:permitted_drift".into(),
+            Value::Nat(Nat::from(PERMITTED_DRIFT)),
+        ),
+    ]);
+    metadata
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_name() -> String {
+    ledger::with(|ledger| ledger.metadata().name.clone().unwrap_or_default())
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_symbol() -> String {
+    ledger::with(|ledger| ledger.metadata().symbol.clone().unwrap_or_default())
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_description() -> Option<String> {
+    None
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_logo() -> Option<String> {
+    ledger::with(|ledger| ledger.metadata().logo.clone())
+}
+
+/// Unlike `dip721_total_supply`, burned nfts are not counted.
+#[query()]
+#[candid_method(query)]
+fn icrc7_total_supply() -> Nat {
+    ledger::with(|ledger| Nat::from(ledger.unburned_tokens_count()))
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_supply_cap() -> Option<Nat> {
+    ledger::with(|ledger| ledger.metadata().max_supply.clone())
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_max_query_batch_size() -> Option<Nat> {
+    Some(Nat::from(MAX_QUERY_BATCH_SIZE))
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_max_update_batch_size() -> Option<Nat> {
+    Some(Nat::from(MAX_UPDATE_BATCH_SIZE))
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_default_take_value() -> Option<Nat> {
+    Some(Nat::from(DEFAULT_TAKE_VALUE))
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_max_take_value() -> Option<Nat> {
+    Some(Nat::from(MAX_TAKE_VALUE))
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_max_memo_size() -> Option<Nat> {
+    Some(Nat::from(MAX_MEMO_SIZE))
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_atomic_batch_transfers() -> Option<bool> {
+    Some(false)
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_tx_window() -> Option<Nat> {
+    Some(Nat::from(TX_WINDOW))
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_permitted_drift() -> Option<Nat> {
+    Some(Nat::from(PERMITTED_DRIFT))
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_supported_standards() -> Vec<Standard> {
+    let mut standards = vec![Standard {
+        name: "ICRC-7".into(),
+        url: ICRC7_URL.into(),
+    }];
+    // blocks are only recorded with the tx log
+    if ledger::with(|ledger| ledger.history().logs()) {
+        standards.push(Standard {
+            name: "ICRC-3".into(),
+            url: ICRC3_URL.into(),
+        });
+    }
+    if cfg!(feature = "approval") {
+        standards.push(Standard {
+            name: "ICRC-37".into(),
+            url: ICRC37_URL.into(),
+        });
+    }
+    standards
+}
+
+/// Burned or unknown nfts have no metadata.
+#[query()]
+#[candid_method(query)]
+fn icrc7_token_metadata(token_ids: Vec<TokenIdentifier>) -> Vec<Option<Vec<(String, Value)>>> {
+    check_query_batch_size(&token_ids);
+    ledger::with(|ledger| {
+        token_ids
+            .iter()
+            .map(|token_id| {
+                ledger
+                    .token_metadata(token_id)
+                    .ok()
+                    .filter(|token_metadata| !token_metadata.is_burned)
+                    .map(|token_metadata| {
+                        token_metadata
+                            .properties
+                            .into_iter()
+                            .map(|(key, value)| (key, value.into()))
+                            .collect()
+                    })
+            })
+            .collect()
+    })
+}
+
+// ==================================================================================================
+// ownership
+// ==================================================================================================
+#[query()]
+#[candid_method(query)]
+fn icrc7_owner_of(token_ids: Vec<TokenIdentifier>) -> Vec<Option<Account>> {
+    check_query_batch_size(&token_ids);
+    ledger::with(|ledger| {
+        token_ids
+            .iter()
+            .map(|token_id| ledger.owner_account_of(token_id).ok().flatten())
+            .collect()
+    })
+}
+
+#[query()]
+#[candid_method(query)]
+fn icrc7_balance_of(accounts: Vec<Account>) -> Vec<Nat> {
+    check_query_batch_size(&accounts);
+    ledger::with(|ledger| {
+        accounts
+            .into_iter()
+            .map(|account| {
+                account.normalized().map_or(Nat::from(0u8), |account| {
+                    Nat::from(ledger.account_balance(&account))
+                })
+            })
+            .collect()
+    })
+}
+
+/// Existing nfts in ascending order, starting after `prev`.
+#[query()]
+#[candid_method(query)]
+fn icrc7_tokens(prev: Option<TokenIdentifier>, take: Option<Nat>) -> Vec<TokenIdentifier> {
+    let take = take_value(take);
+    ledger::with(|ledger| ledger.unburned_token_identifiers(prev, take))
+}
+
+/// Nfts of `account` in ascending order, starting after `prev`.
+#[query()]
+#[candid_method(query)]
+fn icrc7_tokens_of(
+    account: Account,
+    prev: Option<TokenIdentifier>,
+    take: Option<Nat>,
+) -> Vec<TokenIdentifier> {
+    let take = take_value(take);
+    ledger::with(|ledger| {
+        account
+            .normalized()
+            .map(|account| ledger.account_token_identifiers_page(&account, prev, take))
+            .unwrap_or_default()
+    })
+}
+
+// ==================================================================================================
+// transfer
+// ==================================================================================================
+/// Not atomic: every transfer gets its own result, in the same order as `args`.
+#[update]
+#[candid_method(update)]
+fn icrc7_transfer(args: Vec<TransferArg>) -> Vec<Option<TransferResult>> {
+    if let Some(reply) = oversized_update_batch(&args) {
+        return reply;
+    }
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let now = time();
+        ledger.prune_recent_transfers(now.saturating_sub(TX_WINDOW + PERMITTED_DRIFT));
+        args.into_iter()
+            .map(|arg| Some(transfer(ledger, caller, now, arg)))
+            .collect()
+    })
+}
+
+fn transfer(
+    ledger: &mut ledger::Ledger,
+    caller: Principal,
+    now: u64,
+    arg: TransferArg,
+) -> TransferResult {
+    check_memo(&arg.memo)?;
+    // only transfers with a `created_at_time` are deduplicated
+    let dedup = match arg.created_at_time {
+        Some(created_at_time) => {
+            check_created_at_time(created_at_time, now)?;
+            let encoded_arg = encode_one(&arg).expect("couldn't encode transfer arg");
+            if let Some(duplicate_of) =
+                ledger.recent_transfer(created_at_time, caller, encoded_arg.clone())
+            {
+                return Err(TransferError::Duplicate { duplicate_of });
+            }
+            Some((created_at_time, encoded_arg))
+        }
+        None => None,
+    };
+
+    let from = Account {
+        owner: caller,
+        subaccount: arg.from_subaccount,
+    }
+    .normalized()
+    .map_err(|_| TransferError::Unauthorized)?;
+    let to = arg
+        .to
+        .normalized()
+        .map_err(|_| TransferError::InvalidRecipient)?;
+    if to == from {
+        return Err(TransferError::InvalidRecipient);
+    }
+    match ledger.owner_account_of(&arg.token_id) {
+        Ok(Some(owner)) if owner == from => {}
+        Ok(Some(_)) => return Err(TransferError::Unauthorized),
+        Ok(None) | Err(_) => return Err(TransferError::NonExistingTokenId),
+    }
+
+    let tx_id = transfer_between_accounts(
+        ledger,
+        caller,
+        "transfer",
+        from,
+        to,
+        arg.token_id,
+        icrc_details(arg.memo, arg.created_at_time),
+    )
+    .map_err(vetoed)?;
+    if let Some((created_at_time, encoded_arg)) = dedup {
+        ledger.add_recent_transfer(created_at_time, caller, encoded_arg, tx_id.clone());
+    }
+    Ok(tx_id)
+}
@@ -155,28 +155,40 @@ fn is_approved_for_all(owner: Principal, operator: Principal) -> Result<bool, Nf
     dip721_is_approved_for_all(owner, operator)
 }
 
+#[cfg(feature = "history")]
+#[query()]
+#[candid_method(query)]
+fn transaction(tx_id: Nat) -> Result<TxEvent, NftError> {
+    dip721_transaction(tx_id)
+}
+
 // ======================
 //      UPDATE CALLS
 // ======================
 
+#[cfg(feature = "approval")]
 #[update(name = "approve")]
 #[candid_method(update, rename = "approve")]
 fn approve(operator: Principal, token_identifier: TokenIdentifier) -> Result<Nat, NftError> {
-    dip721_approve(operator, token_identifier)
+    dip721_approve(operator, token_identifier, None)
 }
 
+#[cfg(feature = "approval")]
 #[update(name = "setApprovalForAll")]
 #[candid_method(update, rename = "setApprovalForAll")]
 fn set_approval_for_all(operator: Principal, is_approved: bool) -> Result<Nat, NftError> {
-    dip721_set_approval_for_all(operator, is_approved)
+    dip721_set_approval_for_all(operator, is_approved, None)
 }
 
+// the ext interface takes over `transfer`
+#[cfg(not(feature = "ext"))]
 #[update(name = "transfer")]
 #[candid_method(update, rename = "transfer")]
 fn transfer(to: Principal, token_identifier: TokenIdentifier) -> Result<Nat, NftError> {
     dip721_transfer(to, token_identifier)
 }
 
+#[cfg(feature = "approval")]
 #[update(name = "transferFrom")]
 #[candid_method(update, rename = "transferFrom")]
 fn transfer_from(
@@ -187,6 +199,7 @@ fn transfer_from(
     dip721_transfer_from(owner, to, token_identifier)
 }
 
+#[cfg(feature = "mint")]
 #[update(name = "mint", guard = "is_canister_custodian")]
 #[candid_method(update, rename = "mint")]
 fn mint(
@@ -197,6 +210,7 @@ fn mint(
     dip721_mint(to, token_identifier, properties)
 }
 
+#[cfg(feature = "burn")]
 #[update(name = "burn")]
 #[candid_method(update, rename = "burn")]
 fn burn(token_identifier: TokenIdentifier) -> Result<Nat, NftError> {
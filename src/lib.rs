@@ -0,0 +1,2750 @@
+//! DIP721 ledger and endpoints, see `spec.md`.
+//! Canisters depending on this library get every endpoint compiled in by the cargo features and can add
+//! their own next to them, reusing the `ledger` state and the `dip721_*` handlers.
+
+use cap_sdk::{DetailValue, IndefiniteEvent};
+#[cfg(feature = "build-info")]
+use compile_time_run::run_command_str;
+use ic_cdk::api::call::ManualReply;
+use ic_cdk::api::{caller, canister_balance128, time, trap};
+use ic_cdk::export::candid::{candid_method, CandidType, Deserialize, Int, Nat};
+use ic_cdk::export::Principal;
+#[cfg(feature = "lifecycle")]
+use ic_cdk_macros::{init, post_upgrade, pre_upgrade};
+use ic_cdk_macros::{query, update};
+use sha2::{Digest, Sha224};
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ops::Not;
+pub use types::*;
+
+mod cap;
+#[cfg(feature = "ext")]
+mod ext;
+pub mod hooks;
+mod icrc3;
+#[cfg(feature = "approval")]
+mod icrc37;
+mod icrc7;
+#[cfg(feature = "legacy")]
+mod legacy;
+mod migrations;
+mod subscriptions;
+mod timer;
+#[cfg(feature = "v1")]
+mod v1;
+
+pub mod types {
+    use super::*;
+    #[derive(CandidType, Deserialize)]
+    pub struct InitArgs {
+        pub name: Option<String>,
+        pub logo: Option<String>,
+        pub symbol: Option<String>,
+        pub custodians: Option<HashSet<Principal>>,
+        pub cap: Option<Principal>,
+        pub max_supply: Option<Nat>,
+        pub history: Option<HistoryBackend>, // defaults to `Both`
+    }
+    /// Given with the service init type on upgrade, its fields are a subset of `InitArgs`.
+    #[derive(CandidType, Deserialize)]
+    pub struct UpgradeArgs {
+        pub cap: Option<Principal>, // switches the cap router, kept when `None`
+    }
+    /// Where the txs are recorded, every tx gets a tx id whatever the backend.
+    #[derive(CandidType, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+    pub enum HistoryBackend {
+        Disabled,
+        Log, // in-canister tx log, behind the history endpoints and the icrc3 blocks
+        Cap,
+        #[default]
+        Both,
+    }
+    impl HistoryBackend {
+        pub fn logs(self) -> bool {
+            matches!(self, Self::Log | Self::Both)
+        }
+
+        pub fn forwards_to_cap(self) -> bool {
+            matches!(self, Self::Cap | Self::Both)
+        }
+    }
+    #[derive(CandidType, Default, Deserialize)]
+    pub struct Metadata {
+        pub name: Option<String>,
+        pub logo: Option<String>,
+        pub symbol: Option<String>,
+        pub custodians: HashSet<Principal>,
+        pub max_supply: Option<Nat>, // minted nfts, burned ones included
+        pub created_at: u64,
+        pub upgraded_at: u64,
+        pub history: HistoryBackend,
+        pub cap: Option<Principal>, // cap router, `None` for the mainnet one
+    }
+    #[derive(CandidType)]
+    pub struct Stats {
+        pub total_transactions: Nat,
+        pub total_supply: Nat,
+        pub cycles: Nat,
+        pub total_unique_holders: Nat,
+    }
+    pub type TokenIdentifier = Nat;
+    pub type Subaccount = Vec<u8>;
+    #[derive(CandidType, Clone, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    pub struct Account {
+        pub owner: Principal,
+        pub subaccount: Option<Subaccount>, // `None` is the default subaccount
+    }
+    impl Account {
+        /// Checks the subaccount length and maps the all-zero subaccount to the default `None`.
+        pub fn normalized(self) -> Result<Self, NftError> {
+            let subaccount = match self.subaccount {
+                Some(subaccount) if subaccount.len() != 32 => {
+                    return Err(NftError::InvalidSubaccount)
+                }
+                Some(subaccount) if subaccount.iter().all(|byte| *byte == 0) => None,
+                subaccount => subaccount,
+            };
+            Ok(Self {
+                owner: self.owner,
+                subaccount,
+            })
+        }
+
+        /// sha224("\x0Aaccount-id" + principal + subaccount), the default subaccount is all zeros.
+        pub fn hash(&self) -> Vec<u8> {
+            let mut hasher = Sha224::new();
+            hasher.update(b"\x0Aaccount-id");
+            hasher.update(self.owner.as_slice());
+            hasher.update(self.subaccount.as_deref().unwrap_or(&[0; 32]));
+            hasher.finalize().to_vec()
+        }
+    }
+    impl From<Principal> for Account {
+        fn from(owner: Principal) -> Self {
+            Self {
+                owner,
+                subaccount: None,
+            }
+        }
+    }
+    #[derive(CandidType, Clone, Deserialize)]
+    pub enum GenericValue {
+        BoolContent(bool),
+        TextContent(String),
+        BlobContent(Vec<u8>),
+        Principal(Principal),
+        Nat8Content(u8),
+        Nat16Content(u16),
+        Nat32Content(u32),
+        Nat64Content(u64),
+        NatContent(Nat),
+        Int8Content(i8),
+        Int16Content(i16),
+        Int32Content(i32),
+        Int64Content(i64),
+        IntContent(Int),
+        FloatContent(f64), // motoko only support f64
+        NestedContent(Vec<(String, GenericValue)>),
+    }
+    /// ICRC-3 value, used by the icrc metadata and the block log.
+    #[derive(CandidType, Clone, Deserialize)]
+    pub enum Value {
+        Blob(Vec<u8>),
+        Text(String),
+        Nat(Nat),
+        Int(Int),
+        Array(Vec<Value>),
+        Map(Vec<(String, Value)>),
+    }
+    impl From<GenericValue> for Value {
+        fn from(value: GenericValue) -> Self {
+            match value {
+                GenericValue::BoolContent(value) => Value::Nat(Nat::from(value as u8)),
+                GenericValue::TextContent(value) => Value::Text(value),
+                GenericValue::BlobContent(value) => Value::Blob(value),
+                GenericValue::Principal(value) => Value::Blob(value.as_slice().to_vec()),
+                GenericValue::Nat8Content(value) => Value::Nat(Nat::from(value)),
+                GenericValue::Nat16Content(value) => Value::Nat(Nat::from(value)),
+                GenericValue::Nat32Content(value) => Value::Nat(Nat::from(value)),
+                GenericValue::Nat64Content(value) => Value::Nat(Nat::from(value)),
+                GenericValue::NatContent(value) => Value::Nat(value),
+                GenericValue::Int8Content(value) => Value::Int(Int::from(value)),
+                GenericValue::Int16Content(value) => Value::Int(Int::from(value)),
+                GenericValue::Int32Content(value) => Value::Int(Int::from(value)),
+                GenericValue::Int64Content(value) => Value::Int(Int::from(value)),
+                GenericValue::IntContent(value) => Value::Int(value),
+                GenericValue::FloatContent(value) => Value::Text(value.to_string()), // no float in icrc values
+                GenericValue::NestedContent(values) => Value::Map(
+                    values
+                        .into_iter()
+                        .map(|(key, value)| (key, value.into()))
+                        .collect(),
+                ),
+            }
+        }
+    }
+    /// Embedding canisters keep their own per nft data in `properties` rather than changing this struct,
+    /// it is stored as is in stable memory.
+    /// The canister should match with the signature defined in `spec.md` in order to be considered as a DIP721 contract.
+    #[derive(CandidType, Deserialize)]
+    pub struct TokenMetadata {
+        pub token_identifier: TokenIdentifier,
+        pub owner: Option<Principal>,
+        pub owner_subaccount: Option<Subaccount>, // `None` is the default subaccount
+        pub operator: Option<Principal>,          // latest approved operator
+        pub operators: Vec<Principal>,            // in approval order
+        pub is_burned: bool,
+        pub properties: Vec<(String, GenericValue)>,
+        pub minted_at: u64,
+        pub minted_by: Principal,
+        pub transferred_at: Option<u64>,
+        pub transferred_by: Option<Principal>,
+        pub approved_at: Option<u64>,
+        pub approved_by: Option<Principal>,
+        pub burned_at: Option<u64>,
+        pub burned_by: Option<Principal>,
+    }
+    #[derive(CandidType, Clone, Deserialize)]
+    pub struct TxEvent {
+        pub time: u64,
+        pub caller: Principal,
+        pub operation: String,
+        pub details: Vec<(String, GenericValue)>,
+    }
+    #[derive(CandidType, Deserialize)]
+    pub struct MintRequest {
+        pub to: Principal,
+        pub token_identifier: TokenIdentifier,
+        pub properties: Vec<(String, GenericValue)>,
+    }
+    #[derive(CandidType, Deserialize)]
+    pub struct TransferRequest {
+        pub to: Principal,
+        pub token_identifier: TokenIdentifier,
+    }
+    #[derive(CandidType, Deserialize)]
+    pub struct TransferFromRequest {
+        pub owner: Principal,
+        pub to: Principal,
+        pub token_identifier: TokenIdentifier,
+    }
+    #[derive(CandidType)]
+    pub struct TxIdRange {
+        pub start: Nat,
+        pub end: Nat, // exclusive
+    }
+    #[derive(CandidType)]
+    pub struct TxEventPage {
+        pub events: Vec<(Nat, TxEvent)>,
+        pub next_cursor: Option<Nat>,
+    }
+    #[derive(CandidType, Clone, Copy, Deserialize, PartialEq, Eq)]
+    pub enum EventType {
+        Mint,
+        Transfer, // `transferFrom` included
+        Burn,
+        Approve, // `setApprovalForAll` and `revoke` included
+    }
+    impl EventType {
+        pub fn of(operation: &str) -> Option<Self> {
+            match operation {
+                "mint" => Some(EventType::Mint),
+                "transfer" | "transferFrom" => Some(EventType::Transfer),
+                "burn" => Some(EventType::Burn),
+                "approve" | "setApprovalForAll" | "revoke" => Some(EventType::Approve),
+                _ => None,
+            }
+        }
+    }
+    #[derive(CandidType, Clone, Deserialize)]
+    pub struct Subscriber {
+        pub event_types: Vec<EventType>,
+        pub cursor: Nat,   // next tx id to deliver
+        pub failures: u32, // consecutive failed deliveries
+        pub retry_at: u64,
+    }
+    #[derive(CandidType)]
+    pub struct CapOutboxStats {
+        pub pending: u64, // events not accepted by cap yet
+        pub failed: u32,  // consecutive failed inserts
+        pub retry_at: Option<u64>,
+        pub last_error: Option<String>, // the cap numbering mismatch halting the flushes, if any
+        pub resync_cursor: Option<Nat>, // next tx id the running re-sync replays, flushes wait for it
+    }
+    #[derive(CandidType)]
+    pub enum SupportedInterface {
+        Approval,
+        Mint,
+        Burn,
+        TransactionHistory,
+    }
+    #[derive(CandidType)]
+    pub enum NftError {
+        UnauthorizedOwner,
+        UnauthorizedOperator,
+        OwnerNotFound,
+        OperatorNotFound,
+        TokenNotFound,
+        ExistedNFT,
+        SelfApprove,
+        SelfTransfer,
+        TxNotFound,
+        InvalidExpiry,
+        MaxSupplyReached,
+        InvalidSubaccount,
+        Other(String), // hook vetoes and cap re-sync rejections
+        TransferRejected,
+        TransferNotReturned, // rejected by the receiver canister, the nft couldn't be transferred back
+    }
+}
+
+pub mod ledger {
+    use super::*;
+    use ic_cdk::export::candid::{decode_one, encode_one};
+    use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
+    use ic_stable_structures::storable::Bound;
+    use ic_stable_structures::writer::Writer;
+    use ic_stable_structures::{
+        DefaultMemoryImpl, Memory as _, StableBTreeMap, StableCell, Storable,
+    };
+    use std::borrow::Cow;
+    use std::convert::TryFrom;
+
+    type Memory = VirtualMemory<DefaultMemoryImpl>;
+    type TokenCache<K> = StableBTreeMap<Candid<(K, TokenIdentifier)>, Candid<()>, Memory>;
+    type Balances<K> = StableBTreeMap<Candid<K>, Candid<u64>, Memory>;
+    type TxIndex<K> = StableBTreeMap<Candid<(K, Nat)>, Candid<()>, Memory>;
+    type RecentTransfers = StableBTreeMap<Candid<(u64, Principal, Vec<u8>)>, Candid<Nat>, Memory>; // (created_at_time, caller, encoded arg) -> tx id
+
+    const MAX_PAGE_SIZE: usize = 100;
+    const MAX_SWEEP_SIZE: usize = 100;
+
+    // heap state (metadata, tx count, cap root bucket being installed) is written here in pre_upgrade only
+    const UPGRADES: MemoryId = MemoryId::new(0);
+    const TOKENS: MemoryId = MemoryId::new(1);
+    const OWNERS: MemoryId = MemoryId::new(2);
+    const OPERATORS: MemoryId = MemoryId::new(3);
+    const BALANCES: MemoryId = MemoryId::new(4);
+    const TXS: MemoryId = MemoryId::new(5);
+    const TOKEN_TXS: MemoryId = MemoryId::new(6);
+    const CALLER_TXS: MemoryId = MemoryId::new(7);
+    const COUNTERPARTY_TXS: MemoryId = MemoryId::new(8);
+    const APPROVALS_FOR_ALL: MemoryId = MemoryId::new(9);
+    const APPROVAL_EXPIRIES: MemoryId = MemoryId::new(10);
+    const EXPIRING_APPROVALS: MemoryId = MemoryId::new(11);
+    const SUBACCOUNT_OWNERS: MemoryId = MemoryId::new(12);
+    const SUBACCOUNT_BALANCES: MemoryId = MemoryId::new(13);
+    const RECENT_TRANSFERS: MemoryId = MemoryId::new(14);
+    const UNBURNED_TOKENS: MemoryId = MemoryId::new(15);
+    const BLOCKS: MemoryId = MemoryId::new(16);
+    const ACCOUNT_HASHES: MemoryId = MemoryId::new(17);
+    const SUBSCRIBERS: MemoryId = MemoryId::new(18);
+    const CAP_OUTBOX: MemoryId = MemoryId::new(19);
+    const CAP_RESYNC: MemoryId = MemoryId::new(20);
+    const CAP_MISALIGNED: MemoryId = MemoryId::new(21);
+    const SCHEMA_VERSION: MemoryId = MemoryId::new(22);
+
+    thread_local!(
+        static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
+            RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
+        static LEDGER: RefCell<Ledger> = RefCell::new(Ledger::default());
+    );
+
+    pub fn with<T, F: FnOnce(&Ledger) -> T>(f: F) -> T {
+        LEDGER.with(|ledger| f(&ledger.borrow()))
+    }
+
+    pub fn with_mut<T, F: FnOnce(&mut Ledger) -> T>(f: F) -> T {
+        LEDGER.with(|ledger| f(&mut ledger.borrow_mut()))
+    }
+
+    fn memory(memory_id: MemoryId) -> Memory {
+        MEMORY_MANAGER.with(|memory_manager| memory_manager.borrow().get(memory_id))
+    }
+
+    /// `stable_save` output starts with the candid magic bytes, the memory manager writes `MGR` instead.
+    /// Must be checked before the ledger is touched, since that initializes the memory manager.
+    pub fn is_legacy_stable_memory() -> bool {
+        let mut magic = [0; 4];
+        if ic_cdk::api::stable::stable_size() > 0 {
+            ic_cdk::api::stable::stable_read(0, &mut magic);
+        }
+        magic.eq(b"DIDL")
+    }
+
+    /// Any candid value stored in stable memory.
+    #[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct Candid<T>(pub T);
+
+    impl<T: CandidType + for<'de> Deserialize<'de>> Storable for Candid<T> {
+        fn to_bytes(&self) -> Cow<'_, [u8]> {
+            Cow::Owned(encode_one(&self.0).expect("couldn't encode stable value"))
+        }
+
+        fn from_bytes(bytes: Cow<[u8]>) -> Self {
+            Self(decode_one(&bytes).expect("couldn't decode stable value"))
+        }
+
+        const BOUND: Bound = Bound::Unbounded;
+    }
+
+    pub struct Ledger {
+        pub metadata: Metadata,
+        pub tokens: StableBTreeMap<Candid<TokenIdentifier>, Candid<TokenMetadata>, Memory>, // recommend to have sequential id
+        pub unburned_tokens: StableBTreeMap<Candid<TokenIdentifier>, Candid<()>, Memory>, // quick lookup, burned nfts are removed
+        pub owners: TokenCache<Principal>,    // quick lookup
+        pub operators: TokenCache<Principal>, // quick lookup
+        pub balances: Balances<Principal>,    // one entry per holder
+        pub subaccount_owners: TokenCache<(Principal, Subaccount)>, // quick lookup, default subaccounts stay in `owners`
+        pub subaccount_balances: Balances<(Principal, Subaccount)>, // one entry per holding subaccount
+        pub account_hashes: StableBTreeMap<Candid<Vec<u8>>, Candid<Account>, Memory>, // `Account::hash` -> every account that received an nft
+        pub txs: StableBTreeMap<Candid<Nat>, Candid<TxEvent>, Memory>,
+        pub token_txs: TxIndex<TokenIdentifier>, // quick lookup
+        pub caller_txs: TxIndex<Principal>,      // quick lookup
+        pub counterparty_txs: TxIndex<Principal>, // quick lookup
+        pub approvals_for_all:
+            StableBTreeMap<Candid<(Principal, Principal)>, Candid<Option<u64>>, Memory>, // (owner, operator) -> expires_at
+        pub approval_expiries:
+            StableBTreeMap<Candid<(TokenIdentifier, Principal)>, Candid<u64>, Memory>, // (token, operator) -> expires_at
+        pub expiring_approvals:
+            StableBTreeMap<Candid<(u64, TokenIdentifier, Principal)>, Candid<()>, Memory>, // sweep order
+        pub recent_transfers: RecentTransfers,
+        pub blocks: StableBTreeMap<Candid<u64>, Candid<Value>, Memory>, // icrc3 block log, chained by `phash`
+        pub subscribers: StableBTreeMap<Candid<Principal>, Candid<Subscriber>, Memory>,
+        pub cap_outbox: StableBTreeMap<Candid<Nat>, Candid<TxEvent>, Memory>, // tx id -> event not accepted by cap yet
+        pub cap_resync: StableCell<Candid<Option<(Nat, Nat)>>, Memory>, // (first, next) tx id to replay into cap, `None` when no re-sync runs
+        pub cap_misaligned: StableCell<Candid<Option<(Nat, Nat)>>, Memory>, // (tx id, cap tx id) cap numbered differently
+        pub schema_version: StableCell<u32, Memory>,                        // see `migrations`
+        pub tx_count: Nat,
+    }
+
+    impl Default for Ledger {
+        fn default() -> Self {
+            Self {
+                metadata: Metadata::default(),
+                tokens: StableBTreeMap::init(memory(TOKENS)),
+                unburned_tokens: StableBTreeMap::init(memory(UNBURNED_TOKENS)),
+                owners: StableBTreeMap::init(memory(OWNERS)),
+                operators: StableBTreeMap::init(memory(OPERATORS)),
+                balances: StableBTreeMap::init(memory(BALANCES)),
+                subaccount_owners: StableBTreeMap::init(memory(SUBACCOUNT_OWNERS)),
+                subaccount_balances: StableBTreeMap::init(memory(SUBACCOUNT_BALANCES)),
+                account_hashes: StableBTreeMap::init(memory(ACCOUNT_HASHES)),
+                recent_transfers: StableBTreeMap::init(memory(RECENT_TRANSFERS)),
+                blocks: StableBTreeMap::init(memory(BLOCKS)),
+                subscribers: StableBTreeMap::init(memory(SUBSCRIBERS)),
+                cap_outbox: StableBTreeMap::init(memory(CAP_OUTBOX)),
+                cap_resync: StableCell::init(memory(CAP_RESYNC), Candid(None))
+                    .expect("couldn't init the cap re-sync cursor"),
+                cap_misaligned: StableCell::init(memory(CAP_MISALIGNED), Candid(None))
+                    .expect("couldn't init the cap misalignment"),
+                // states saved before the tag are version 1, fresh installs are tagged in `init_metadata`
+                schema_version: StableCell::init(memory(SCHEMA_VERSION), 1)
+                    .expect("couldn't init the schema version"),
+                txs: StableBTreeMap::init(memory(TXS)),
+                token_txs: StableBTreeMap::init(memory(TOKEN_TXS)),
+                caller_txs: StableBTreeMap::init(memory(CALLER_TXS)),
+                counterparty_txs: StableBTreeMap::init(memory(COUNTERPARTY_TXS)),
+                approvals_for_all: StableBTreeMap::init(memory(APPROVALS_FOR_ALL)),
+                approval_expiries: StableBTreeMap::init(memory(APPROVAL_EXPIRIES)),
+                expiring_approvals: StableBTreeMap::init(memory(EXPIRING_APPROVALS)),
+                tx_count: Nat::default(),
+            }
+        }
+    }
+
+    /// `cap_sdk::Archive` saved next to the heap ledger, mirrored since its fields are private.
+    #[derive(CandidType, Deserialize)]
+    pub struct LegacyCapArchive {
+        pub router: Option<Principal>,
+        pub creation_cycles: Option<u64>,
+        pub uninitialized_root_bucket: Option<Principal>,
+        pub root_bucket: Option<Principal>,
+        pub local_buffer: Vec<IndefiniteEvent>, // the last txs, cap didn't accept them yet
+    }
+
+    /// Heap ledger saved with `stable_save` before the ledger moved into stable memory.
+    #[derive(CandidType, Deserialize)]
+    pub struct LegacyLedger {
+        pub metadata: LegacyMetadata,
+        pub tokens: HashMap<TokenIdentifier, LegacyTokenMetadata>,
+        pub owners: HashMap<Principal, HashSet<TokenIdentifier>>,
+        pub operators: HashMap<Principal, HashSet<TokenIdentifier>>,
+        pub tx_count: Nat,
+    }
+
+    /// `Metadata` of the heap ledger, txs were always logged and forwarded to cap back then.
+    #[derive(CandidType, Deserialize)]
+    pub struct LegacyMetadata {
+        pub name: Option<String>,
+        pub logo: Option<String>,
+        pub symbol: Option<String>,
+        pub custodians: HashSet<Principal>,
+        pub created_at: u64,
+        pub upgraded_at: u64,
+    }
+
+    impl From<LegacyMetadata> for Metadata {
+        fn from(metadata: LegacyMetadata) -> Self {
+            Self {
+                name: metadata.name,
+                logo: metadata.logo,
+                symbol: metadata.symbol,
+                custodians: metadata.custodians,
+                max_supply: None,
+                created_at: metadata.created_at,
+                upgraded_at: metadata.upgraded_at,
+                history: HistoryBackend::Both,
+                cap: None,
+            }
+        }
+    }
+
+    /// `TokenMetadata` of the heap ledger, tokens had a single operator back then.
+    #[derive(CandidType, Deserialize)]
+    pub struct LegacyTokenMetadata {
+        pub token_identifier: TokenIdentifier,
+        pub owner: Option<Principal>,
+        pub operator: Option<Principal>,
+        pub is_burned: bool,
+        pub properties: Vec<(String, GenericValue)>,
+        pub minted_at: u64,
+        pub minted_by: Principal,
+        pub transferred_at: Option<u64>,
+        pub transferred_by: Option<Principal>,
+        pub approved_at: Option<u64>,
+        pub approved_by: Option<Principal>,
+        pub burned_at: Option<u64>,
+        pub burned_by: Option<Principal>,
+    }
+
+    impl From<LegacyTokenMetadata> for TokenMetadata {
+        fn from(token_metadata: LegacyTokenMetadata) -> Self {
+            Self {
+                token_identifier: token_metadata.token_identifier,
+                owner: token_metadata.owner,
+                owner_subaccount: None,
+                operator: token_metadata.operator,
+                operators: token_metadata.operator.into_iter().collect(),
+                is_burned: token_metadata.is_burned,
+                properties: token_metadata.properties,
+                minted_at: token_metadata.minted_at,
+                minted_by: token_metadata.minted_by,
+                transferred_at: token_metadata.transferred_at,
+                transferred_by: token_metadata.transferred_by,
+                approved_at: token_metadata.approved_at,
+                approved_by: token_metadata.approved_by,
+                burned_at: token_metadata.burned_at,
+                burned_by: token_metadata.burned_by,
+            }
+        }
+    }
+
+    impl Ledger {
+        pub fn init_metadata(&mut self, default_custodian: Principal, args: Option<InitArgs>) {
+            let metadata = self.metadata_mut();
+            metadata.custodians.insert(default_custodian);
+            let mut history = HistoryBackend::default();
+            if let Some(args) = args {
+                metadata.name = args.name;
+                metadata.logo = args.logo;
+                metadata.symbol = args.symbol;
+                metadata.max_supply = args.max_supply;
+                if let Some(custodians) = args.custodians {
+                    for custodians in custodians {
+                        metadata.custodians.insert(custodians);
+                    }
+                }
+                // the root bucket is looked up on the router, or created, by the first flush
+                metadata.cap = args.cap;
+                history = args.history.unwrap_or(history);
+            }
+            metadata.history = history;
+            metadata.created_at = time();
+            metadata.upgraded_at = time();
+            self.set_schema_version(migrations::VERSION);
+        }
+
+        pub fn save_heap_state(&self, created_bucket: Option<Principal>) -> Result<(), String> {
+            let bytes = encode_one((&self.metadata, &self.tx_count, created_bucket))
+                .map_err(|err| err.to_string())?;
+            let mut upgrades = memory(UPGRADES);
+            let mut writer = Writer::new(&mut upgrades, 0);
+            writer
+                .write(&(bytes.len() as u64).to_le_bytes())
+                .and_then(|_| writer.write(&bytes))
+                .map_err(|err| format!("{:?}", err))
+        }
+
+        pub fn load_heap_state(&mut self) -> Result<Option<Principal>, String> {
+            let upgrades = memory(UPGRADES);
+            let mut len = [0; 8];
+            upgrades.read(0, &mut len);
+            let mut bytes = vec![0; u64::from_le_bytes(len) as usize];
+            upgrades.read(8, &mut bytes);
+            let (metadata, tx_count, created_bucket) =
+                decode_one::<(Metadata, Nat, Option<Principal>)>(&bytes)
+                    .map_err(|err| err.to_string())?;
+            self.metadata = metadata;
+            self.tx_count = tx_count;
+            Ok(created_bucket)
+        }
+
+        pub fn schema_version(&self) -> u32 {
+            *self.schema_version.get()
+        }
+
+        pub fn set_schema_version(&mut self, version: u32) {
+            self.schema_version
+                .set(version)
+                .expect("couldn't write the schema version");
+        }
+
+        /// Returns the root bucket created for the router but not installed yet.
+        pub fn restore_legacy(
+            &mut self,
+            legacy_ledger: LegacyLedger,
+            legacy_cap: LegacyCapArchive,
+        ) -> Option<Principal> {
+            self.metadata = legacy_ledger.metadata.into();
+            self.metadata.cap = legacy_cap.router;
+            self.tx_count = legacy_ledger.tx_count;
+            // the buffer holds the last txs, in order
+            let first_tx_id = self.tx_count.clone() - legacy_cap.local_buffer.len();
+            for (offset, event) in legacy_cap.local_buffer.into_iter().enumerate() {
+                let tx_event = TxEvent {
+                    time: time(),
+                    caller: event.caller,
+                    operation: event.operation,
+                    details: event
+                        .details
+                        .into_iter()
+                        .map(|(key, value)| (key, GenericValue::from(value)))
+                        .collect(),
+                };
+                self.cap_outbox
+                    .insert(Candid(first_tx_id.clone() + offset), Candid(tx_event));
+            }
+            for (token_identifier, token_metadata) in legacy_ledger.tokens {
+                self.add_token_metadata(token_identifier, token_metadata.into());
+            }
+            for (owner, token_identifiers) in legacy_ledger.owners {
+                for token_identifier in token_identifiers {
+                    self.update_owner_cache(&token_identifier, None, Some(owner));
+                }
+            }
+            for (operator, token_identifiers) in legacy_ledger.operators {
+                for token_identifier in token_identifiers {
+                    self.update_operator_cache(&token_identifier, None, Some(operator));
+                }
+            }
+            legacy_cap.uninitialized_root_bucket
+        }
+
+        pub fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+
+        pub fn metadata_mut(&mut self) -> &mut Metadata {
+            &mut self.metadata
+        }
+
+        pub fn history(&self) -> HistoryBackend {
+            self.metadata.history
+        }
+
+        pub fn tokens_count(&self) -> usize {
+            self.tokens.len() as usize
+        }
+
+        pub fn unburned_tokens_count(&self) -> usize {
+            self.unburned_tokens.len() as usize
+        }
+
+        /// Up to `take` unburned token identifiers in ascending order, starting after `prev`.
+        pub fn unburned_token_identifiers(
+            &self,
+            prev: Option<TokenIdentifier>,
+            take: usize,
+        ) -> Vec<TokenIdentifier> {
+            use std::ops::Bound; // not the `Storable` one
+
+            let start = prev.map_or(Bound::Unbounded, |prev| Bound::Excluded(Candid(prev)));
+            self.unburned_tokens
+                .range((start, Bound::Unbounded))
+                .take(take)
+                .map(|(token_identifier, _)| token_identifier.0)
+                .collect()
+        }
+
+        pub fn is_within_max_supply(&self, new_tokens_count: usize) -> bool {
+            self.metadata.max_supply.as_ref().is_none_or(|max_supply| {
+                Nat::from(self.tokens_count() + new_tokens_count).le(max_supply)
+            })
+        }
+
+        /// One past the highest identifier, so assigned ids never collide with custom ones.
+        pub fn next_token_identifier(&self) -> TokenIdentifier {
+            self.tokens
+                .last_key_value()
+                .map_or_else(TokenIdentifier::default, |(token_identifier, _)| {
+                    token_identifier.0 + 1
+                })
+        }
+
+        pub fn tx_count(&self) -> Nat {
+            self.tx_count.clone()
+        }
+
+        pub fn is_token_existed(&self, token_identifier: &TokenIdentifier) -> bool {
+            self.tokens.contains_key(&Candid(token_identifier.clone()))
+        }
+
+        pub fn token_metadata(
+            &self,
+            token_identifier: &TokenIdentifier,
+        ) -> Result<TokenMetadata, NftError> {
+            self.tokens
+                .get(&Candid(token_identifier.clone()))
+                .map(|token_metadata| self.without_expired_operators(token_metadata.0))
+                .ok_or(NftError::TokenNotFound)
+        }
+
+        fn without_expired_operators(&self, mut token_metadata: TokenMetadata) -> TokenMetadata {
+            let token_identifier = token_metadata.token_identifier.clone();
+            token_metadata
+                .operators
+                .retain(|operator| self.is_approval_live(&token_identifier, operator));
+            token_metadata.operator = token_metadata.operators.last().copied();
+            token_metadata
+        }
+
+        pub fn add_token_metadata(
+            &mut self,
+            token_identifier: TokenIdentifier,
+            token_metadata: TokenMetadata,
+        ) {
+            if !token_metadata.is_burned {
+                self.unburned_tokens
+                    .insert(Candid(token_identifier.clone()), Candid(()));
+            }
+            self.tokens
+                .insert(Candid(token_identifier), Candid(token_metadata));
+        }
+
+        fn update_token_metadata<F: FnOnce(&mut TokenMetadata)>(
+            &mut self,
+            token_identifier: &TokenIdentifier,
+            f: F,
+        ) {
+            let token_identifier = Candid(token_identifier.clone());
+            let mut token_metadata = self
+                .tokens
+                .get(&token_identifier)
+                .expect("couldn't find token metadata");
+            f(&mut token_metadata.0);
+            self.tokens.insert(token_identifier, token_metadata);
+        }
+
+        pub fn owners_count(&self) -> usize {
+            (self.balances.len() + self.subaccount_balances.len()) as usize
+        }
+
+        pub fn owner_token_identifiers(
+            &self,
+            owner: &Principal,
+        ) -> Result<HashSet<TokenIdentifier>, NftError> {
+            Some(cached_token_identifiers(&self.owners, owner))
+                .filter(|token_identifiers| !token_identifiers.is_empty())
+                .ok_or(NftError::OwnerNotFound)
+        }
+
+        pub fn owner_of(
+            &self,
+            token_identifier: &TokenIdentifier,
+        ) -> Result<Option<Principal>, NftError> {
+            self.token_metadata(token_identifier)
+                .map(|token_metadata| token_metadata.owner)
+        }
+
+        /// Owner principal only when the nft sits in its default subaccount,
+        /// principal endpoints can't move nfts out of other subaccounts.
+        pub fn owner_of_default_subaccount(
+            &self,
+            token_identifier: &TokenIdentifier,
+        ) -> Result<Option<Principal>, NftError> {
+            self.token_metadata(token_identifier).map(|token_metadata| {
+                token_metadata
+                    .owner
+                    .filter(|_| token_metadata.owner_subaccount.is_none())
+            })
+        }
+
+        pub fn owner_account_of(
+            &self,
+            token_identifier: &TokenIdentifier,
+        ) -> Result<Option<Account>, NftError> {
+            self.token_metadata(token_identifier).map(|token_metadata| {
+                token_metadata.owner.map(|owner| Account {
+                    owner,
+                    subaccount: token_metadata.owner_subaccount,
+                })
+            })
+        }
+
+        /// Accounts holding at least one nft.
+        #[cfg(feature = "ext")]
+        pub fn holders(&self) -> Vec<Account> {
+            self.balances
+                .iter()
+                .map(|(owner, _)| Account::from(owner.0))
+                .chain(self.subaccount_balances.iter().map(|(account, _)| Account {
+                    owner: account.0 .0,
+                    subaccount: Some(account.0 .1),
+                }))
+                .collect()
+        }
+
+        pub fn account_token_identifiers(
+            &self,
+            account: &Account,
+        ) -> Result<HashSet<TokenIdentifier>, NftError> {
+            match &account.subaccount {
+                None => self.owner_token_identifiers(&account.owner),
+                Some(subaccount) => Some(cached_token_identifiers(
+                    &self.subaccount_owners,
+                    &(account.owner, subaccount.clone()),
+                ))
+                .filter(|token_identifiers| !token_identifiers.is_empty())
+                .ok_or(NftError::OwnerNotFound),
+            }
+        }
+
+        /// Up to `take` token identifiers of `account` in ascending order, starting after `prev`.
+        pub fn account_token_identifiers_page(
+            &self,
+            account: &Account,
+            prev: Option<TokenIdentifier>,
+            take: usize,
+        ) -> Vec<TokenIdentifier> {
+            match &account.subaccount {
+                None => cached_token_identifiers_page(&self.owners, &account.owner, prev, take),
+                Some(subaccount) => cached_token_identifiers_page(
+                    &self.subaccount_owners,
+                    &(account.owner, subaccount.clone()),
+                    prev,
+                    take,
+                ),
+            }
+        }
+
+        pub fn account_balance(&self, account: &Account) -> u64 {
+            match &account.subaccount {
+                None => self.balances.get(&Candid(account.owner)),
+                Some(subaccount) => self
+                    .subaccount_balances
+                    .get(&Candid((account.owner, subaccount.clone()))),
+            }
+            .map_or(0, |balance| balance.0)
+        }
+
+        pub fn owner_token_metadata(
+            &self,
+            owner: &Principal,
+        ) -> Result<Vec<TokenMetadata>, NftError> {
+            self.owner_token_identifiers(owner)?
+                .iter()
+                .map(|token_identifier| self.token_metadata(token_identifier))
+                .collect()
+        }
+
+        pub fn update_owner_cache(
+            &mut self,
+            token_identifier: &TokenIdentifier,
+            old_owner: Option<Principal>,
+            new_owner: Option<Principal>,
+        ) {
+            if let Some(old_owner) = old_owner {
+                uncache_token(
+                    &mut self.owners,
+                    &mut self.balances,
+                    old_owner,
+                    token_identifier,
+                );
+            }
+            if let Some(new_owner) = new_owner {
+                self.index_account(&new_owner.into());
+                cache_token(
+                    &mut self.owners,
+                    &mut self.balances,
+                    new_owner,
+                    token_identifier,
+                );
+            }
+        }
+
+        /// Same as `update_owner_cache` for accounts, default subaccounts are kept in `owners`.
+        pub fn update_account_cache(
+            &mut self,
+            token_identifier: &TokenIdentifier,
+            old_owner: Option<Account>,
+            new_owner: Option<Account>,
+        ) {
+            if let Some(old_owner) = old_owner {
+                match old_owner.subaccount {
+                    None => self.update_owner_cache(token_identifier, Some(old_owner.owner), None),
+                    Some(subaccount) => uncache_token(
+                        &mut self.subaccount_owners,
+                        &mut self.subaccount_balances,
+                        (old_owner.owner, subaccount),
+                        token_identifier,
+                    ),
+                }
+            }
+            if let Some(new_owner) = new_owner {
+                match new_owner.subaccount {
+                    None => self.update_owner_cache(token_identifier, None, Some(new_owner.owner)),
+                    Some(ref subaccount) => {
+                        self.index_account(&new_owner);
+                        cache_token(
+                            &mut self.subaccount_owners,
+                            &mut self.subaccount_balances,
+                            (new_owner.owner, subaccount.clone()),
+                            token_identifier,
+                        )
+                    }
+                }
+            }
+        }
+
+        /// Accounts stay indexed once they received an nft, account identifiers of former holders keep resolving.
+        pub fn index_account(&mut self, account: &Account) {
+            let hash = Candid(account.hash());
+            if !self.account_hashes.contains_key(&hash) {
+                self.account_hashes.insert(hash, Candid(account.clone()));
+            }
+        }
+
+        /// Account whose `Account::hash` is `hash`, if it ever received an nft.
+        #[cfg(feature = "ext")]
+        pub fn account_of_hash(&self, hash: &[u8]) -> Option<Account> {
+            self.account_hashes
+                .get(&Candid(hash.to_vec()))
+                .map(|account| account.0)
+        }
+
+        pub fn operator_token_identifiers(
+            &self,
+            operator: &Principal,
+        ) -> Result<HashSet<TokenIdentifier>, NftError> {
+            Some(
+                cached_token_identifiers(&self.operators, operator)
+                    .into_iter()
+                    .filter(|token_identifier| self.is_approval_live(token_identifier, operator))
+                    .collect::<HashSet<_>>(),
+            )
+            .filter(|token_identifiers| token_identifiers.is_empty().not())
+            .ok_or(NftError::OperatorNotFound)
+        }
+
+        pub fn operator_of(
+            &self,
+            token_identifier: &TokenIdentifier,
+        ) -> Result<Option<Principal>, NftError> {
+            self.token_metadata(token_identifier)
+                .map(|token_metadata| token_metadata.operator)
+        }
+
+        pub fn operators_of(
+            &self,
+            token_identifier: &TokenIdentifier,
+        ) -> Result<Vec<Principal>, NftError> {
+            self.token_metadata(token_identifier)
+                .map(|token_metadata| token_metadata.operators)
+        }
+
+        pub fn operator_token_metadata(
+            &self,
+            operator: &Principal,
+        ) -> Result<Vec<TokenMetadata>, NftError> {
+            self.operator_token_identifiers(operator)?
+                .iter()
+                .map(|token_identifier| self.token_metadata(token_identifier))
+                .collect()
+        }
+
+        pub fn update_operator_cache(
+            &mut self,
+            token_identifier: &TokenIdentifier,
+            old_operator: Option<Principal>,
+            new_operator: Option<Principal>,
+        ) {
+            if let Some(old_operator) = old_operator {
+                self.operators
+                    .remove(&Candid((old_operator, token_identifier.clone())));
+            }
+            if let Some(new_operator) = new_operator {
+                self.operators
+                    .insert(Candid((new_operator, token_identifier.clone())), Candid(()));
+            }
+        }
+
+        /// Adds `operator` to the token operators, approving it again moves it to the end
+        /// and replaces the previous expiry.
+        pub fn approve(
+            &mut self,
+            approved_by: Principal,
+            token_identifier: &TokenIdentifier,
+            operator: Principal,
+            expires_at: Option<u64>,
+        ) {
+            self.clear_approval_expiry(token_identifier, &operator);
+            if let Some(expires_at) = expires_at {
+                self.approval_expiries.insert(
+                    Candid((token_identifier.clone(), operator)),
+                    Candid(expires_at),
+                );
+                self.expiring_approvals.insert(
+                    Candid((expires_at, token_identifier.clone(), operator)),
+                    Candid(()),
+                );
+                timer::schedule(expires_at);
+            }
+            self.update_token_metadata(token_identifier, |token_metadata| {
+                token_metadata
+                    .operators
+                    .retain(|approved| approved.ne(&operator));
+                token_metadata.operators.push(operator);
+                token_metadata.operator = Some(operator);
+                token_metadata.approved_by = Some(approved_by);
+                token_metadata.approved_at = Some(time());
+            });
+        }
+
+        pub fn revoke(
+            &mut self,
+            revoked_by: Principal,
+            token_identifier: &TokenIdentifier,
+            operator: &Principal,
+        ) {
+            self.clear_approval_expiry(token_identifier, operator);
+            self.update_token_metadata(token_identifier, |token_metadata| {
+                token_metadata
+                    .operators
+                    .retain(|approved| approved.ne(operator));
+                token_metadata.operator = token_metadata.operators.last().copied();
+                token_metadata.approved_by = Some(revoked_by);
+                token_metadata.approved_at = Some(time());
+            });
+        }
+
+        fn clear_approval_expiry(
+            &mut self,
+            token_identifier: &TokenIdentifier,
+            operator: &Principal,
+        ) {
+            if let Some(expires_at) = self
+                .approval_expiries
+                .remove(&Candid((token_identifier.clone(), *operator)))
+            {
+                self.expiring_approvals.remove(&Candid((
+                    expires_at.0,
+                    token_identifier.clone(),
+                    *operator,
+                )));
+            }
+        }
+
+        pub fn approval_expires_at(
+            &self,
+            token_identifier: &TokenIdentifier,
+            operator: &Principal,
+        ) -> Option<u64> {
+            self.approval_expiries
+                .get(&Candid((token_identifier.clone(), *operator)))
+                .map(|expires_at| expires_at.0)
+        }
+
+        fn is_approval_live(
+            &self,
+            token_identifier: &TokenIdentifier,
+            operator: &Principal,
+        ) -> bool {
+            self.approval_expiries
+                .get(&Candid((token_identifier.clone(), *operator)))
+                .is_none_or(|expires_at| expires_at.0 > time())
+        }
+
+        /// Removes up to `MAX_SWEEP_SIZE` lapsed approvals from the token operators and the operator cache,
+        /// reads already hide them so this only keeps storage from growing.
+        pub fn sweep_expired_approvals(&mut self) {
+            let now = time();
+            let expired: Vec<(u64, TokenIdentifier, Principal)> = self
+                .expiring_approvals
+                .iter()
+                .take_while(|(approval, _)| approval.0 .0 <= now)
+                .take(MAX_SWEEP_SIZE)
+                .map(|(approval, _)| approval.0)
+                .collect();
+            for (expires_at, token_identifier, operator) in expired {
+                self.expiring_approvals.remove(&Candid((
+                    expires_at,
+                    token_identifier.clone(),
+                    operator,
+                )));
+                self.approval_expiries
+                    .remove(&Candid((token_identifier.clone(), operator)));
+                // transfers and burns only clean the cache for approvals that were still live,
+                // removing an entry that's already gone does nothing
+                self.update_operator_cache(&token_identifier, Some(operator), None);
+                let is_operator = self
+                    .tokens
+                    .get(&Candid(token_identifier.clone()))
+                    .is_some_and(|token_metadata| token_metadata.0.operators.contains(&operator));
+                if is_operator {
+                    self.update_token_metadata(&token_identifier, |token_metadata| {
+                        token_metadata
+                            .operators
+                            .retain(|approved| approved.ne(&operator));
+                        token_metadata.operator = token_metadata.operators.last().copied();
+                    });
+                }
+            }
+            self.schedule_sweep();
+        }
+
+        /// Arms the timer for the next approval to lapse, right away when a full batch was swept.
+        pub fn schedule_sweep(&self) {
+            if let Some((approval, _)) = self.expiring_approvals.iter().next() {
+                timer::schedule(approval.0 .0);
+            }
+        }
+
+        /// Operators `owner` has approved for all, lapsed ones included.
+        pub fn approvals_for_all_of(&self, owner: &Principal) -> Vec<Principal> {
+            self.approvals_for_all
+                .range(Candid((*owner, Principal::management_canister()))..)
+                .take_while(|(approval, _)| approval.0 .0.eq(owner))
+                .map(|(approval, _)| approval.0 .1)
+                .collect()
+        }
+
+        pub fn is_approved_for_all(&self, owner: &Principal, operator: &Principal) -> bool {
+            self.approvals_for_all
+                .get(&Candid((*owner, *operator)))
+                .is_some_and(|expires_at| expires_at.0.is_none_or(|expires_at| expires_at > time()))
+        }
+
+        /// Owner level approval, it covers every nft of `owner` including the ones received later.
+        pub fn set_approval_for_all(
+            &mut self,
+            owner: Principal,
+            operator: Principal,
+            is_approved: bool,
+            expires_at: Option<u64>,
+        ) {
+            if is_approved {
+                self.approvals_for_all
+                    .insert(Candid((owner, operator)), Candid(expires_at));
+            } else {
+                self.approvals_for_all.remove(&Candid((owner, operator)));
+            }
+        }
+
+        pub fn mint(
+            &mut self,
+            minted_by: Principal,
+            to: Principal,
+            token_identifier: &TokenIdentifier,
+            properties: Vec<(String, GenericValue)>,
+        ) -> Result<(), NftError> {
+            hooks::with(|hooks| {
+                hooks.before_mint(self, minted_by, &to, token_identifier, &properties)
+            })?;
+            self.add_minted_token(minted_by, to, token_identifier, properties);
+            hooks::with(|hooks| hooks.after_mint(self, minted_by, &to, token_identifier));
+            Ok(())
+        }
+
+        /// Mints every item or none of them, the `before_mint` hooks of all items run first.
+        pub fn mint_batch(
+            &mut self,
+            minted_by: Principal,
+            items: Vec<MintRequest>,
+        ) -> Result<Vec<(Principal, TokenIdentifier)>, NftError> {
+            for item in items.iter() {
+                hooks::with(|hooks| {
+                    hooks.before_mint(
+                        self,
+                        minted_by,
+                        &item.to,
+                        &item.token_identifier,
+                        &item.properties,
+                    )
+                })?;
+            }
+            Ok(items
+                .into_iter()
+                .map(|item| {
+                    let MintRequest {
+                        to,
+                        token_identifier,
+                        properties,
+                    } = item;
+                    self.add_minted_token(minted_by, to, &token_identifier, properties);
+                    hooks::with(|hooks| hooks.after_mint(self, minted_by, &to, &token_identifier));
+                    (to, token_identifier)
+                })
+                .collect())
+        }
+
+        fn add_minted_token(
+            &mut self,
+            minted_by: Principal,
+            to: Principal,
+            token_identifier: &TokenIdentifier,
+            properties: Vec<(String, GenericValue)>,
+        ) {
+            self.add_token_metadata(
+                token_identifier.clone(),
+                TokenMetadata {
+                    token_identifier: token_identifier.clone(),
+                    owner: Some(to),
+                    owner_subaccount: None,
+                    operator: None,
+                    operators: vec![],
+                    properties,
+                    is_burned: false,
+                    minted_at: time(),
+                    minted_by,
+                    transferred_at: None,
+                    transferred_by: None,
+                    approved_at: None,
+                    approved_by: None,
+                    burned_at: None,
+                    burned_by: None,
+                },
+            );
+        }
+
+        pub fn transfer(
+            &mut self,
+            transferred_by: Principal,
+            token_identifier: &TokenIdentifier,
+            new_owner: Account,
+        ) -> Result<(), NftError> {
+            let old_owner = self
+                .owner_account_of(token_identifier)?
+                .ok_or(NftError::OwnerNotFound)?;
+            hooks::with(|hooks| {
+                hooks.before_transfer(
+                    self,
+                    transferred_by,
+                    &old_owner,
+                    &new_owner,
+                    token_identifier,
+                )
+            })?;
+            self.update_token_metadata(token_identifier, |token_metadata| {
+                token_metadata.owner = Some(new_owner.owner);
+                token_metadata.owner_subaccount = new_owner.subaccount.clone();
+                token_metadata.transferred_by = Some(transferred_by);
+                token_metadata.transferred_at = Some(time());
+                token_metadata.operator = None;
+                token_metadata.operators.clear();
+            });
+            hooks::with(|hooks| {
+                hooks.after_transfer(
+                    self,
+                    transferred_by,
+                    &old_owner,
+                    &new_owner,
+                    token_identifier,
+                )
+            });
+            Ok(())
+        }
+
+        pub fn burn(
+            &mut self,
+            burned_by: Principal,
+            token_identifier: &TokenIdentifier,
+        ) -> Result<(), NftError> {
+            let owner = self
+                .owner_account_of(token_identifier)?
+                .ok_or(NftError::OwnerNotFound)?;
+            hooks::with(|hooks| hooks.before_burn(self, burned_by, &owner, token_identifier))?;
+            self.update_token_metadata(token_identifier, |token_metadata| {
+                token_metadata.owner = None;
+                token_metadata.owner_subaccount = None;
+                token_metadata.operator = None;
+                token_metadata.operators.clear();
+                token_metadata.is_burned = true;
+                token_metadata.burned_by = Some(burned_by);
+                token_metadata.burned_at = Some(time());
+            });
+            self.unburned_tokens
+                .remove(&Candid(token_identifier.clone()));
+            hooks::with(|hooks| hooks.after_burn(self, burned_by, &owner, token_identifier));
+            Ok(())
+        }
+
+        /// Tx id of the identical transfer `caller` already sent with the same `created_at_time`.
+        pub fn recent_transfer(
+            &self,
+            created_at_time: u64,
+            caller: Principal,
+            arg: Vec<u8>,
+        ) -> Option<Nat> {
+            self.recent_transfers
+                .get(&Candid((created_at_time, caller, arg)))
+                .map(|tx_id| tx_id.0)
+        }
+
+        pub fn add_recent_transfer(
+            &mut self,
+            created_at_time: u64,
+            caller: Principal,
+            arg: Vec<u8>,
+            tx_id: Nat,
+        ) {
+            self.recent_transfers
+                .insert(Candid((created_at_time, caller, arg)), Candid(tx_id));
+        }
+
+        /// Drops up to `MAX_SWEEP_SIZE` transfers created before `cutoff`, they are rejected as too old anyway.
+        pub fn prune_recent_transfers(&mut self, cutoff: u64) {
+            let expired: Vec<_> = self
+                .recent_transfers
+                .iter()
+                .take_while(|(transfer, _)| transfer.0 .0 < cutoff)
+                .take(MAX_SWEEP_SIZE)
+                .map(|(transfer, _)| transfer)
+                .collect();
+            for transfer in expired {
+                self.recent_transfers.remove(&transfer);
+            }
+        }
+
+        pub fn blocks_count(&self) -> u64 {
+            self.blocks.len()
+        }
+
+        pub fn block(&self, index: u64) -> Option<Value> {
+            self.blocks.get(&Candid(index)).map(|block| block.0)
+        }
+
+        /// Index and hash of the latest block.
+        pub fn tip(&self) -> Option<(u64, icrc3::Hash)> {
+            self.blocks
+                .last_key_value()
+                .map(|(index, block)| (index.0, icrc3::hash_value(&block.0)))
+        }
+
+        pub fn subscribers(&self) -> Vec<(Principal, Subscriber)> {
+            self.subscribers
+                .iter()
+                .map(|(principal, subscriber)| (principal.0, subscriber.0))
+                .collect()
+        }
+
+        pub fn subscriber(&self, principal: &Principal) -> Option<Subscriber> {
+            self.subscribers
+                .get(&Candid(*principal))
+                .map(|subscriber| subscriber.0)
+        }
+
+        pub fn set_subscriber(&mut self, principal: Principal, subscriber: Subscriber) {
+            self.subscribers
+                .insert(Candid(principal), Candid(subscriber));
+        }
+
+        pub fn remove_subscriber(&mut self, principal: &Principal) {
+            self.subscribers.remove(&Candid(*principal));
+        }
+
+        pub fn cap_outbox_len(&self) -> u64 {
+            self.cap_outbox.len()
+        }
+
+        /// Up to `take` events awaiting cap, oldest first.
+        pub fn cap_outbox(&self, take: usize) -> Vec<(Nat, TxEvent)> {
+            self.cap_outbox
+                .iter()
+                .take(take)
+                .map(|(tx_id, tx_event)| (tx_id.0, tx_event.0))
+                .collect()
+        }
+
+        pub fn remove_from_cap_outbox(&mut self, tx_id: &Nat) {
+            self.cap_outbox.remove(&Candid(tx_id.clone()));
+        }
+
+        /// Queues a logged tx for cap again.
+        pub fn requeue_for_cap(&mut self, tx_id: &Nat) -> Result<(), NftError> {
+            let tx_event = self.tx(tx_id)?;
+            self.cap_outbox
+                .insert(Candid(tx_id.clone()), Candid(tx_event));
+            Ok(())
+        }
+
+        /// Removes up to `limit` queued txs before `tx_id`, returns how many were removed.
+        pub fn clear_cap_outbox(&mut self, tx_id: &Nat, limit: usize) -> usize {
+            let tx_ids: Vec<_> = self
+                .cap_outbox
+                .range(..Candid(tx_id.clone()))
+                .take(limit)
+                .map(|(tx_id, _)| tx_id)
+                .collect();
+            for tx_id in tx_ids.iter() {
+                self.cap_outbox.remove(tx_id);
+            }
+            tx_ids.len()
+        }
+
+        /// First and next tx id of the running re-sync.
+        pub fn cap_resync(&self) -> Option<(Nat, Nat)> {
+            self.cap_resync.get().0.clone()
+        }
+
+        pub fn set_cap_resync(&mut self, resync: Option<(Nat, Nat)>) {
+            self.cap_resync
+                .set(Candid(resync))
+                .expect("couldn't write the cap re-sync cursor");
+        }
+
+        /// First tx cap recorded under another tx id, flushes halt until a re-sync realigns the cap history.
+        pub fn cap_misaligned(&self) -> Option<(Nat, Nat)> {
+            self.cap_misaligned.get().0.clone()
+        }
+
+        pub fn set_cap_misaligned(&mut self, misaligned: Option<(Nat, Nat)>) {
+            self.cap_misaligned
+                .set(Candid(misaligned))
+                .expect("couldn't write the cap misalignment");
+        }
+
+        /// Id of the first tx in the tx log, `tx_count` when it's empty.
+        /// Canisters upgraded from the heap ledger only logged the txs recorded since.
+        pub fn first_logged_tx_id(&self) -> Nat {
+            self.txs
+                .first_key_value()
+                .map_or_else(|| self.tx_count(), |(tx_id, _)| tx_id.0)
+        }
+
+        pub fn inc_tx(&mut self) -> Nat {
+            self.tx_count += 1;
+            self.tx_count.clone()
+        }
+
+        pub fn txs_by_token(
+            &self,
+            token_identifier: &TokenIdentifier,
+            cursor: Option<Nat>,
+            limit: u32,
+        ) -> TxEventPage {
+            txs_page(self, &self.token_txs, token_identifier, cursor, limit)
+        }
+
+        pub fn txs_by_caller(
+            &self,
+            caller: &Principal,
+            cursor: Option<Nat>,
+            limit: u32,
+        ) -> TxEventPage {
+            txs_page(self, &self.caller_txs, caller, cursor, limit)
+        }
+
+        pub fn txs_by_counterparty(
+            &self,
+            counterparty: &Principal,
+            cursor: Option<Nat>,
+            limit: u32,
+        ) -> TxEventPage {
+            txs_page(self, &self.counterparty_txs, counterparty, cursor, limit)
+        }
+
+        pub fn tx(&self, tx_id: &Nat) -> Result<TxEvent, NftError> {
+            self.txs
+                .get(&Candid(tx_id.clone()))
+                .map(|tx_event| tx_event.0)
+                .ok_or(NftError::TxNotFound)
+        }
+
+        /// Hands the event over to the history backend and returns its tx id.
+        pub fn add_tx(
+            &mut self,
+            caller: Principal,
+            operation: String,
+            details: Vec<(String, GenericValue)>,
+        ) -> Nat {
+            let tx_id: Nat = self.inc_tx() - 1;
+            let tx_event = TxEvent {
+                time: time(),
+                caller,
+                operation,
+                details,
+            };
+            let history = self.history();
+            if history.forwards_to_cap() {
+                self.cap_outbox
+                    .insert(Candid(tx_id.clone()), Candid(tx_event.clone()));
+                cap::schedule();
+            }
+            if history.logs() {
+                self.log_tx(&tx_id, tx_event);
+                subscriptions::schedule(self);
+            }
+            tx_id
+        }
+
+        /// Appends the event to the transaction log, its indexes and the icrc3 blocks.
+        fn log_tx(&mut self, tx_id: &Nat, tx_event: TxEvent) {
+            let phash = self.tip().map(|(_, hash)| hash);
+            self.blocks.insert(
+                Candid(self.blocks.len()),
+                Candid(icrc3::block(
+                    phash,
+                    tx_event.time,
+                    tx_event.caller,
+                    &tx_event.operation,
+                    &tx_event.details,
+                )),
+            );
+            icrc3::certify(self.tip());
+
+            self.caller_txs
+                .insert(Candid((tx_event.caller, tx_id.clone())), Candid(()));
+            for (key, value) in tx_event.details.iter() {
+                match (key.as_str(), value) {
+                    ("token_identifier", GenericValue::NatContent(token_identifier)) => {
+                        self.token_txs.insert(
+                            Candid((token_identifier.clone(), tx_id.clone())),
+                            Candid(()),
+                        );
+                    }
+                    ("owner" | "to" | "operator", GenericValue::Principal(counterparty)) => {
+                        self.counterparty_txs
+                            .insert(Candid((*counterparty, tx_id.clone())), Candid(()));
+                    }
+                    _ => {}
+                }
+            }
+            self.txs.insert(Candid(tx_id.clone()), Candid(tx_event));
+        }
+    }
+
+    /// Walks `index` from `cursor` (inclusive) and returns up to `limit` events of `key`,
+    /// `next_cursor` is the tx id the following page starts from.
+    fn txs_page<K: CandidType + for<'de> Deserialize<'de> + Clone + Ord>(
+        ledger: &Ledger,
+        index: &TxIndex<K>,
+        key: &K,
+        cursor: Option<Nat>,
+        limit: u32,
+    ) -> TxEventPage {
+        let limit = (limit as usize).min(MAX_PAGE_SIZE);
+        let mut tx_ids: Vec<Nat> = index
+            .range(Candid((key.clone(), cursor.unwrap_or_default()))..)
+            .map(|(entry, _)| entry.0)
+            .take_while(|(entry_key, _)| entry_key.eq(key))
+            .map(|(_, tx_id)| tx_id)
+            .take(limit + 1)
+            .collect();
+        let next_cursor = if tx_ids.len() > limit {
+            tx_ids.pop()
+        } else {
+            None
+        };
+        TxEventPage {
+            events: tx_ids
+                .into_iter()
+                .map(|tx_id| {
+                    let tx_event = ledger
+                        .tx(&tx_id)
+                        .unwrap_or_else(|_| trap("couldn't find indexed tx"));
+                    (tx_id, tx_event)
+                })
+                .collect(),
+            next_cursor,
+        }
+    }
+
+    impl From<GenericValue> for DetailValue {
+        fn from(value: GenericValue) -> Self {
+            match value {
+                GenericValue::BoolContent(true) => DetailValue::True,
+                GenericValue::BoolContent(false) => DetailValue::False,
+                GenericValue::TextContent(value) => DetailValue::Text(value),
+                GenericValue::BlobContent(value) => DetailValue::Slice(value),
+                GenericValue::Principal(value) => DetailValue::Principal(value),
+                GenericValue::Nat8Content(value) => DetailValue::U64(value.into()),
+                GenericValue::Nat16Content(value) => DetailValue::U64(value.into()),
+                GenericValue::Nat32Content(value) => DetailValue::U64(value.into()),
+                GenericValue::Nat64Content(value) => DetailValue::U64(value),
+                // cap has no arbitrary precision numbers, keep them as text like token identifiers always were
+                GenericValue::NatContent(value) => DetailValue::Text(value.to_string()),
+                GenericValue::Int8Content(value) => DetailValue::I64(value.into()),
+                GenericValue::Int16Content(value) => DetailValue::I64(value.into()),
+                GenericValue::Int32Content(value) => DetailValue::I64(value.into()),
+                GenericValue::Int64Content(value) => DetailValue::I64(value),
+                GenericValue::IntContent(value) => DetailValue::Text(value.to_string()),
+                GenericValue::FloatContent(value) => DetailValue::Float(value),
+                GenericValue::NestedContent(values) => DetailValue::Vec(
+                    values
+                        .into_iter()
+                        .map(|(key, value)| {
+                            DetailValue::Vec(vec![DetailValue::Text(key), value.into()])
+                        })
+                        .collect(),
+                ),
+            }
+        }
+    }
+
+    /// Inverse of the `DetailValue` conversion, for the events cap-sdk still buffered in the heap ledger.
+    /// The values the heap ledger sent convert back to the same `DetailValue`.
+    impl From<DetailValue> for GenericValue {
+        fn from(value: DetailValue) -> Self {
+            match value {
+                DetailValue::True => GenericValue::BoolContent(true),
+                DetailValue::False => GenericValue::BoolContent(false),
+                DetailValue::Text(value) => GenericValue::TextContent(value),
+                DetailValue::Slice(value) => GenericValue::BlobContent(value),
+                DetailValue::Principal(value) => GenericValue::Principal(value),
+                DetailValue::U64(value) | DetailValue::TokenIdU64(value) => {
+                    GenericValue::Nat64Content(value)
+                }
+                DetailValue::I64(value) => GenericValue::Int64Content(value),
+                DetailValue::Float(value) => GenericValue::FloatContent(value),
+                DetailValue::Vec(values) => GenericValue::NestedContent(
+                    values
+                        .into_iter()
+                        // nested values were sent as `(key, value)` pairs, anything else gets an empty key
+                        .map(|value| match value {
+                            DetailValue::Vec(values) => {
+                                match <[DetailValue; 2]>::try_from(values) {
+                                    Ok([DetailValue::Text(key), value]) => (key, value.into()),
+                                    Ok(pair) => {
+                                        (String::new(), DetailValue::Vec(pair.into()).into())
+                                    }
+                                    Err(values) => (String::new(), DetailValue::Vec(values).into()),
+                                }
+                            }
+                            value => (String::new(), value.into()),
+                        })
+                        .collect(),
+                ),
+            }
+        }
+    }
+
+    /// Walks the entries of `key` in `cache`, each one is a token it owns or operates.
+    fn cached_token_identifiers<K: CandidType + for<'de> Deserialize<'de> + Clone + Ord>(
+        cache: &TokenCache<K>,
+        key: &K,
+    ) -> HashSet<TokenIdentifier> {
+        cache
+            .range(Candid((key.clone(), TokenIdentifier::default()))..)
+            .map(|(entry, _)| entry.0)
+            .take_while(|(cached_by, _)| cached_by.eq(key))
+            .map(|(_, token_identifier)| token_identifier)
+            .collect()
+    }
+
+    /// Up to `take` token identifiers of `key` in `cache` in ascending order, starting after `prev`.
+    fn cached_token_identifiers_page<K: CandidType + for<'de> Deserialize<'de> + Clone + Ord>(
+        cache: &TokenCache<K>,
+        key: &K,
+        prev: Option<TokenIdentifier>,
+        take: usize,
+    ) -> Vec<TokenIdentifier> {
+        use std::ops::Bound; // not the `Storable` one
+
+        let start = match prev {
+            Some(prev) => Bound::Excluded(Candid((key.clone(), prev))),
+            None => Bound::Included(Candid((key.clone(), TokenIdentifier::default()))),
+        };
+        cache
+            .range((start, Bound::Unbounded))
+            .map(|(entry, _)| entry.0)
+            .take_while(|(cached_by, _)| cached_by.eq(key))
+            .map(|(_, token_identifier)| token_identifier)
+            .take(take)
+            .collect()
+    }
+
+    /// Adds the entry of `key` for `token_identifier` and counts it in the balance of `key`.
+    fn cache_token<K: CandidType + for<'de> Deserialize<'de> + Clone + Ord>(
+        cache: &mut TokenCache<K>,
+        balances: &mut Balances<K>,
+        key: K,
+        token_identifier: &TokenIdentifier,
+    ) {
+        let entry = Candid((key.clone(), token_identifier.clone()));
+        if cache.insert(entry, Candid(())).is_none() {
+            let balance = balances.get(&Candid(key.clone())).unwrap_or_default();
+            balances.insert(Candid(key), Candid(balance.0 + 1));
+        }
+    }
+
+    /// Removes the entry of `key` for `token_identifier`, keys left without nfts lose their balance.
+    fn uncache_token<K: CandidType + for<'de> Deserialize<'de> + Clone + Ord>(
+        cache: &mut TokenCache<K>,
+        balances: &mut Balances<K>,
+        key: K,
+        token_identifier: &TokenIdentifier,
+    ) {
+        let entry = Candid((key.clone(), token_identifier.clone()));
+        if cache.remove(&entry).is_some() {
+            let balance = balances
+                .get(&Candid(key.clone()))
+                .expect("couldn't find owner");
+            if balance.0 > 1 {
+                balances.insert(Candid(key), Candid(balance.0 - 1));
+            } else {
+                balances.remove(&Candid(key));
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "lifecycle", init)]
+#[candid_method(init)]
+pub fn init(args: Option<InitArgs>) {
+    ledger::with_mut(|ledger| ledger.init_metadata(caller(), args));
+}
+
+#[cfg(feature = "approval")]
+fn is_valid_expiry(expires_at: Option<u64>) -> Result<(), NftError> {
+    expires_at
+        .is_none_or(|expires_at| expires_at > time())
+        .then_some(())
+        .ok_or(NftError::InvalidExpiry)
+}
+
+pub fn is_canister_custodian() -> Result<(), String> {
+    ledger::with(|ledger| {
+        ledger
+            .metadata()
+            .custodians
+            .contains(&caller())
+            .then_some(())
+            .ok_or_else(|| "Caller is not an custodian of canister".into())
+    })
+}
+
+// ==================================================================================================
+// cover metadata
+// ==================================================================================================
+#[cfg(feature = "build-info")]
+#[query()]
+#[candid_method(query)]
+fn git_commit_hash() -> &'static str {
+    run_command_str!("git", "rev-parse", "HEAD")
+}
+
+#[cfg(feature = "build-info")]
+#[query()]
+#[candid_method(query)]
+fn rust_toolchain_info() -> &'static str {
+    run_command_str!("rustup", "show")
+}
+
+#[cfg(feature = "build-info")]
+#[query()]
+#[candid_method(query)]
+fn dfx_info() -> &'static str {
+    run_command_str!("dfx", "--version")
+}
+
+// ==================================================================================================
+// metadata
+// ==================================================================================================
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_name() -> ManualReply<Option<String>> {
+    ledger::with(|ledger| ManualReply::one(ledger.metadata().name.as_ref()))
+}
+
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_logo() -> ManualReply<Option<String>> {
+    ledger::with(|ledger| ManualReply::one(ledger.metadata().logo.as_ref()))
+}
+
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_symbol() -> ManualReply<Option<String>> {
+    ledger::with(|ledger| ManualReply::one(ledger.metadata().symbol.as_ref()))
+}
+
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_max_supply() -> ManualReply<Option<Nat>> {
+    ledger::with(|ledger| ManualReply::one(ledger.metadata().max_supply.as_ref()))
+}
+
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_custodians() -> ManualReply<HashSet<Principal>> {
+    ledger::with(|ledger| ManualReply::one(&ledger.metadata().custodians))
+}
+
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_metadata() -> ManualReply<Metadata> {
+    ledger::with(|ledger| ManualReply::one(ledger.metadata()))
+}
+
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+pub fn dip721_set_name(name: String) {
+    ledger::with_mut(|ledger| ledger.metadata_mut().name = Some(name));
+}
+
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+pub fn dip721_set_logo(logo: String) {
+    ledger::with_mut(|ledger| ledger.metadata_mut().logo = Some(logo));
+}
+
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+pub fn dip721_set_symbol(symbol: String) {
+    ledger::with_mut(|ledger| ledger.metadata_mut().symbol = Some(symbol));
+}
+
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+pub fn dip721_set_max_supply(max_supply: Option<Nat>) {
+    ledger::with_mut(|ledger| ledger.metadata_mut().max_supply = max_supply);
+}
+
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+pub fn dip721_set_custodians(custodians: HashSet<Principal>) {
+    ledger::with_mut(|ledger| ledger.metadata_mut().custodians = custodians);
+}
+
+/// Switches the cap router, the mainnet one when `None`. Pending and later events go to the root bucket
+/// it hands out, a new root bucket is filled back with `dip721_resync_cap`.
+/// Rejected while a flush awaits cap.
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+pub fn dip721_set_cap(cap: Option<Principal>) {
+    cap::set_router(cap);
+}
+
+// ==================================================================================================
+// stats
+// ==================================================================================================
+/// Returns the total current supply of NFT tokens.
+/// NFTs that are minted and later burned explicitly or sent to the zero address should also count towards totalSupply.
+#[query()]
+#[candid_method(query)]
+pub fn dip721_total_supply() -> Nat {
+    ledger::with(|ledger| Nat::from(ledger.tokens_count()))
+}
+
+#[query()]
+#[candid_method(query)]
+pub fn dip721_total_transactions() -> Nat {
+    ledger::with(|ledger| ledger.tx_count())
+}
+
+#[query()]
+#[candid_method(query)]
+pub fn dip721_cycles() -> Nat {
+    Nat::from(canister_balance128())
+}
+
+#[query()]
+#[candid_method(query)]
+pub fn dip721_total_unique_holders() -> Nat {
+    ledger::with(|ledger| Nat::from(ledger.owners_count()))
+}
+
+#[query()]
+#[candid_method(query)]
+pub fn dip721_stats() -> Stats {
+    Stats {
+        total_transactions: dip721_total_transactions(),
+        total_supply: dip721_total_supply(),
+        cycles: dip721_cycles(),
+        total_unique_holders: dip721_total_unique_holders(),
+    }
+}
+
+// ==================================================================================================
+// supported interfaces
+// ==================================================================================================
+#[query()]
+#[candid_method(query)]
+pub fn dip721_supported_interfaces() -> Vec<SupportedInterface> {
+    let mut interfaces = vec![];
+    if cfg!(feature = "approval") {
+        interfaces.push(SupportedInterface::Approval);
+    }
+    if cfg!(feature = "mint") {
+        interfaces.push(SupportedInterface::Mint);
+    }
+    if cfg!(feature = "burn") {
+        interfaces.push(SupportedInterface::Burn);
+    }
+    if cfg!(feature = "history") && ledger::with(|ledger| ledger.history().logs()) {
+        interfaces.push(SupportedInterface::TransactionHistory);
+    }
+    interfaces
+}
+
+// ==================================================================================================
+// balance
+// ==================================================================================================
+#[query()]
+#[candid_method(query)]
+pub fn dip721_balance_of(owner: Principal) -> Result<Nat, NftError> {
+    ledger::with(|ledger| {
+        ledger
+            .owner_token_identifiers(&owner)
+            .map(|token_identifiers| Nat::from(token_identifiers.len()))
+    })
+}
+
+#[query()]
+#[candid_method(query)]
+pub fn dip721_balance_of_account(account: Account) -> Result<Nat, NftError> {
+    ledger::with(|ledger| {
+        ledger
+            .account_token_identifiers(&account.normalized()?)
+            .map(|token_identifiers| Nat::from(token_identifiers.len()))
+    })
+}
+
+// ==================================================================================================
+// token ownership
+// ==================================================================================================
+#[query()]
+#[candid_method(query)]
+pub fn dip721_owner_of(token_identifier: TokenIdentifier) -> Result<Option<Principal>, NftError> {
+    ledger::with(|ledger| ledger.owner_of(&token_identifier))
+}
+
+#[query()]
+#[candid_method(query)]
+pub fn dip721_owner_account_of(
+    token_identifier: TokenIdentifier,
+) -> Result<Option<Account>, NftError> {
+    ledger::with(|ledger| ledger.owner_account_of(&token_identifier))
+}
+
+#[query()]
+#[candid_method(query)]
+pub fn dip721_operator_of(
+    token_identifier: TokenIdentifier,
+) -> Result<Option<Principal>, NftError> {
+    ledger::with(|ledger| ledger.operator_of(&token_identifier))
+}
+
+#[query()]
+#[candid_method(query)]
+pub fn dip721_operators_of(token_identifier: TokenIdentifier) -> Result<Vec<Principal>, NftError> {
+    ledger::with(|ledger| ledger.operators_of(&token_identifier))
+}
+
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_owner_token_metadata(
+    owner: Principal,
+) -> ManualReply<Result<Vec<TokenMetadata>, NftError>> {
+    ledger::with(|ledger| ManualReply::one(ledger.owner_token_metadata(&owner)))
+}
+
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_operator_token_metadata(
+    operator: Principal,
+) -> ManualReply<Result<Vec<TokenMetadata>, NftError>> {
+    ledger::with(|ledger| ManualReply::one(ledger.operator_token_metadata(&operator)))
+}
+
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_owner_token_identifiers(
+    owner: Principal,
+) -> ManualReply<Result<Vec<TokenIdentifier>, NftError>> {
+    ledger::with(|ledger| ManualReply::one(ledger.owner_token_identifiers(&owner)))
+}
+
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_owner_token_identifiers_of_account(
+    account: Account,
+) -> ManualReply<Result<Vec<TokenIdentifier>, NftError>> {
+    ledger::with(|ledger| {
+        ManualReply::one(
+            account
+                .normalized()
+                .and_then(|account| ledger.account_token_identifiers(&account)),
+        )
+    })
+}
+
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_operator_token_identifiers(
+    operator: Principal,
+) -> ManualReply<Result<Vec<TokenIdentifier>, NftError>> {
+    ledger::with(|ledger| ManualReply::one(ledger.operator_token_identifiers(&operator)))
+}
+
+// ==================================================================================================
+// token metadata
+// ==================================================================================================
+#[query(manual_reply = true)]
+#[candid_method(query)]
+pub fn dip721_token_metadata(
+    token_identifier: TokenIdentifier,
+) -> ManualReply<Result<TokenMetadata, NftError>> {
+    ledger::with(|ledger| ManualReply::one(ledger.token_metadata(&token_identifier)))
+}
+
+// ==================================================================================================
+// approved for all
+// ==================================================================================================
+#[query()]
+#[candid_method(query)]
+pub fn dip721_is_approved_for_all(owner: Principal, operator: Principal) -> Result<bool, NftError> {
+    ledger::with(|ledger| Ok(ledger.is_approved_for_all(&owner, &operator)))
+}
+
+// ==================================================================================================
+// core api
+// ==================================================================================================
+/// `expires_at` is in nanoseconds since the epoch, the approval lapses at that time
+#[cfg(feature = "approval")]
+#[update]
+#[candid_method(update)]
+pub fn dip721_approve(
+    operator: Principal,
+    token_identifier: TokenIdentifier,
+    expires_at: Option<u64>,
+) -> Result<Nat, NftError> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        operator
+            .ne(&caller)
+            .then_some(())
+            .ok_or(NftError::SelfApprove)?;
+        is_valid_expiry(expires_at)?;
+        ledger
+            .owner_of_default_subaccount(&token_identifier)?
+            .eq(&Some(caller))
+            .then_some(())
+            .ok_or(NftError::UnauthorizedOwner)?;
+        ledger.update_operator_cache(&token_identifier, None, Some(operator));
+        ledger.approve(caller, &token_identifier, operator, expires_at);
+
+        let mut details = vec![
+            ("operator".into(), GenericValue::Principal(operator)),
+            (
+                "token_identifier".into(),
+                GenericValue::NatContent(token_identifier),
+            ),
+        ];
+        if let Some(expires_at) = expires_at {
+            details.push(("expires_at".into(), GenericValue::Nat64Content(expires_at)));
+        }
+        Ok(ledger.add_tx(caller, "approve".into(), details))
+    })
+}
+
+/// approves or revokes `operator` on every nft of the caller, including the ones received later,
+/// operators approved per nft with `dip721_approve` are left untouched,
+/// `expires_at` is ignored when revoking
+#[cfg(feature = "approval")]
+#[update]
+#[candid_method(update)]
+pub fn dip721_set_approval_for_all(
+    operator: Principal,
+    is_approved: bool,
+    expires_at: Option<u64>,
+) -> Result<Nat, NftError> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        operator
+            .ne(&caller)
+            .then_some(())
+            .ok_or(NftError::SelfApprove)?;
+        let expires_at = expires_at.filter(|_| is_approved);
+        is_valid_expiry(expires_at)?;
+        ledger.set_approval_for_all(caller, operator, is_approved, expires_at);
+
+        let mut details = vec![
+            ("operator".into(), GenericValue::Principal(operator)),
+            ("is_approved".into(), GenericValue::BoolContent(is_approved)),
+        ];
+        if let Some(expires_at) = expires_at {
+            details.push(("expires_at".into(), GenericValue::Nat64Content(expires_at)));
+        }
+        Ok(ledger.add_tx(caller, "setApprovalForAll".into(), details))
+    })
+}
+
+#[cfg(feature = "approval")]
+#[update]
+#[candid_method(update)]
+pub fn dip721_revoke(
+    operator: Principal,
+    token_identifier: TokenIdentifier,
+) -> Result<Nat, NftError> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        ledger
+            .owner_of_default_subaccount(&token_identifier)?
+            .eq(&Some(caller))
+            .then_some(())
+            .ok_or(NftError::UnauthorizedOwner)?;
+        ledger
+            .operators_of(&token_identifier)?
+            .contains(&operator)
+            .then_some(())
+            .ok_or(NftError::OperatorNotFound)?;
+        ledger.update_operator_cache(&token_identifier, Some(operator), None);
+        ledger.revoke(caller, &token_identifier, &operator);
+
+        Ok(ledger.add_tx(
+            caller,
+            "revoke".into(),
+            vec![
+                ("operator".into(), GenericValue::Principal(operator)),
+                (
+                    "token_identifier".into(),
+                    GenericValue::NatContent(token_identifier),
+                ),
+            ],
+        ))
+    })
+}
+
+#[update]
+#[candid_method(update)]
+pub fn dip721_transfer(to: Principal, token_identifier: TokenIdentifier) -> Result<Nat, NftError> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        to.ne(&caller).then_some(()).ok_or(NftError::SelfTransfer)?;
+        let old_owner = ledger.owner_of_default_subaccount(&token_identifier)?;
+        let old_operators = ledger.operators_of(&token_identifier)?;
+        old_owner
+            .eq(&Some(caller))
+            .then_some(())
+            .ok_or(NftError::UnauthorizedOwner)?;
+        ledger.transfer(caller, &token_identifier, to.into())?;
+        ledger.update_owner_cache(&token_identifier, old_owner, Some(to));
+        for old_operator in old_operators {
+            ledger.update_operator_cache(&token_identifier, Some(old_operator), None);
+        }
+
+        Ok(ledger.add_tx(
+            caller,
+            "transfer".into(),
+            vec![
+                ("owner".into(), GenericValue::Principal(caller)),
+                ("to".into(), GenericValue::Principal(to)),
+                (
+                    "token_identifier".into(),
+                    GenericValue::NatContent(token_identifier),
+                ),
+            ],
+        ))
+    })
+}
+
+#[cfg(feature = "approval")]
+#[update]
+#[candid_method(update)]
+pub fn dip721_transfer_from(
+    owner: Principal,
+    to: Principal,
+    token_identifier: TokenIdentifier,
+) -> Result<Nat, NftError> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        owner.ne(&to).then_some(()).ok_or(NftError::SelfTransfer)?;
+        let old_owner = ledger.owner_of_default_subaccount(&token_identifier)?;
+        let old_operators = ledger.operators_of(&token_identifier)?;
+        old_owner
+            .eq(&Some(owner))
+            .then_some(())
+            .ok_or(NftError::UnauthorizedOwner)?;
+        (old_operators.contains(&caller) || ledger.is_approved_for_all(&owner, &caller))
+            .then_some(())
+            .ok_or(NftError::UnauthorizedOperator)?;
+        ledger.transfer(caller, &token_identifier, to.into())?;
+        ledger.update_owner_cache(&token_identifier, old_owner, Some(to));
+        for old_operator in old_operators {
+            ledger.update_operator_cache(&token_identifier, Some(old_operator), None);
+        }
+
+        Ok(ledger.add_tx(
+            caller,
+            "transferFrom".into(),
+            vec![
+                ("owner".into(), GenericValue::Principal(owner)),
+                ("to".into(), GenericValue::Principal(to)),
+                (
+                    "token_identifier".into(),
+                    GenericValue::NatContent(token_identifier),
+                ),
+            ],
+        ))
+    })
+}
+
+/// moves an nft out of any subaccount of the caller, `None` being the default one, to any account
+#[update]
+#[candid_method(update)]
+pub fn dip721_transfer_to_account(
+    from_subaccount: Option<Subaccount>,
+    to: Account,
+    token_identifier: TokenIdentifier,
+) -> Result<Nat, NftError> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let from = Account {
+            owner: caller,
+            subaccount: from_subaccount,
+        }
+        .normalized()?;
+        let to = to.normalized()?;
+        to.ne(&from).then_some(()).ok_or(NftError::SelfTransfer)?;
+        ledger
+            .owner_account_of(&token_identifier)?
+            .eq(&Some(from.clone()))
+            .then_some(())
+            .ok_or(NftError::UnauthorizedOwner)?;
+
+        transfer_between_accounts(
+            ledger,
+            caller,
+            "transfer",
+            from,
+            to,
+            token_identifier,
+            vec![],
+        )
+    })
+}
+
+/// moves an nft already checked to be owned by `from`, `extra_details` are appended to the tx
+fn transfer_between_accounts(
+    ledger: &mut ledger::Ledger,
+    caller: Principal,
+    operation: &str,
+    from: Account,
+    to: Account,
+    token_identifier: TokenIdentifier,
+    extra_details: Vec<(String, GenericValue)>,
+) -> Result<Nat, NftError> {
+    let old_operators = ledger.operators_of(&token_identifier).unwrap_or_default();
+    ledger.transfer(caller, &token_identifier, to.clone())?;
+    ledger.update_account_cache(&token_identifier, Some(from.clone()), Some(to.clone()));
+    for old_operator in old_operators {
+        ledger.update_operator_cache(&token_identifier, Some(old_operator), None);
+    }
+
+    let mut details = account_details("owner", from);
+    details.extend(account_details("to", to));
+    details.push((
+        "token_identifier".into(),
+        GenericValue::NatContent(token_identifier),
+    ));
+    details.extend(extra_details);
+    Ok(ledger.add_tx(caller, operation.into(), details))
+}
+
+/// `key` holds the principal, non default subaccounts are recorded next to it as `<key>_subaccount`
+fn account_details(key: &str, account: Account) -> Vec<(String, GenericValue)> {
+    let mut details = vec![(key.into(), GenericValue::Principal(account.owner))];
+    if let Some(subaccount) = account.subaccount {
+        details.push((
+            format!("{}_subaccount", key),
+            GenericValue::BlobContent(subaccount),
+        ));
+    }
+    details
+}
+
+/// every item is checked up front and reported on its own, failing items don't stop the batch,
+/// a token listed twice fails the second time with `UnauthorizedOwner` like a second transfer would
+#[update]
+#[candid_method(update)]
+pub fn dip721_transfer_batch(items: Vec<TransferRequest>) -> Vec<Result<Nat, NftError>> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let mut token_identifiers = HashSet::new();
+        let transfers = items
+            .into_iter()
+            .map(|item| -> Result<_, NftError> {
+                item.to
+                    .ne(&caller)
+                    .then_some(())
+                    .ok_or(NftError::SelfTransfer)?;
+                ledger
+                    .owner_of_default_subaccount(&item.token_identifier)?
+                    .eq(&Some(caller))
+                    .then_some(())
+                    .ok_or(NftError::UnauthorizedOwner)?;
+                token_identifiers
+                    .insert(item.token_identifier.clone())
+                    .then_some(())
+                    .ok_or(NftError::UnauthorizedOwner)?;
+                Ok((caller, item.to, item.token_identifier))
+            })
+            .collect();
+
+        transfer_batch(ledger, caller, "transfer", transfers)
+    })
+}
+
+/// same as `dip721_transfer_batch` with the checks of `dip721_transfer_from` for every item
+#[cfg(feature = "approval")]
+#[update]
+#[candid_method(update)]
+pub fn dip721_transfer_from_batch(items: Vec<TransferFromRequest>) -> Vec<Result<Nat, NftError>> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let mut token_identifiers = HashSet::new();
+        let transfers = items
+            .into_iter()
+            .map(|item| -> Result<_, NftError> {
+                item.owner
+                    .ne(&item.to)
+                    .then_some(())
+                    .ok_or(NftError::SelfTransfer)?;
+                ledger
+                    .owner_of_default_subaccount(&item.token_identifier)?
+                    .eq(&Some(item.owner))
+                    .then_some(())
+                    .ok_or(NftError::UnauthorizedOwner)?;
+                (ledger
+                    .operators_of(&item.token_identifier)?
+                    .contains(&caller)
+                    || ledger.is_approved_for_all(&item.owner, &caller))
+                .then_some(())
+                .ok_or(NftError::UnauthorizedOperator)?;
+                token_identifiers
+                    .insert(item.token_identifier.clone())
+                    .then_some(())
+                    .ok_or(NftError::UnauthorizedOwner)?;
+                Ok((item.owner, item.to, item.token_identifier))
+            })
+            .collect();
+
+        transfer_batch(ledger, caller, "transferFrom", transfers)
+    })
+}
+
+/// applies the checked `(owner, to, token_identifier)` transfers, items vetoed by a hook fail on their own
+fn transfer_batch(
+    ledger: &mut ledger::Ledger,
+    caller: Principal,
+    operation: &str,
+    transfers: Vec<Result<(Principal, Principal, TokenIdentifier), NftError>>,
+) -> Vec<Result<Nat, NftError>> {
+    transfers
+        .into_iter()
+        .map(|transfer| {
+            let (owner, to, token_identifier) = transfer?;
+            let old_operators = ledger.operators_of(&token_identifier)?;
+            ledger.transfer(caller, &token_identifier, to.into())?;
+            ledger.update_owner_cache(&token_identifier, Some(owner), Some(to));
+            for old_operator in old_operators {
+                ledger.update_operator_cache(&token_identifier, Some(old_operator), None);
+            }
+
+            Ok(ledger.add_tx(
+                caller,
+                operation.into(),
+                vec![
+                    ("owner".into(), GenericValue::Principal(owner)),
+                    ("to".into(), GenericValue::Principal(to)),
+                    (
+                        "token_identifier".into(),
+                        GenericValue::NatContent(token_identifier),
+                    ),
+                ],
+            ))
+        })
+        .collect()
+}
+
+#[cfg(feature = "mint")]
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+pub fn dip721_mint(
+    to: Principal,
+    token_identifier: TokenIdentifier,
+    properties: Vec<(String, GenericValue)>,
+) -> Result<Nat, NftError> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        ledger
+            .is_token_existed(&token_identifier)
+            .not()
+            .then_some(())
+            .ok_or(NftError::ExistedNFT)?;
+        ledger
+            .is_within_max_supply(1)
+            .then_some(())
+            .ok_or(NftError::MaxSupplyReached)?;
+        ledger.mint(caller, to, &token_identifier, properties)?;
+        ledger.update_owner_cache(&token_identifier, None, Some(to));
+
+        Ok(ledger.add_tx(
+            caller,
+            "mint".into(),
+            vec![
+                ("to".into(), GenericValue::Principal(to)),
+                (
+                    "token_identifier".into(),
+                    GenericValue::NatContent(token_identifier),
+                ),
+            ],
+        ))
+    })
+}
+
+/// same as `dip721_mint` but the canister assigns the next sequential identifier,
+/// it is recorded as `token_identifier` in the returned tx
+#[cfg(feature = "mint")]
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+pub fn dip721_mint_next(
+    to: Principal,
+    properties: Vec<(String, GenericValue)>,
+) -> Result<Nat, NftError> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        ledger
+            .is_within_max_supply(1)
+            .then_some(())
+            .ok_or(NftError::MaxSupplyReached)?;
+        let token_identifier = ledger.next_token_identifier();
+        ledger.mint(caller, to, &token_identifier, properties)?;
+        ledger.update_owner_cache(&token_identifier, None, Some(to));
+
+        Ok(ledger.add_tx(
+            caller,
+            "mint".into(),
+            vec![
+                ("to".into(), GenericValue::Principal(to)),
+                (
+                    "token_identifier".into(),
+                    GenericValue::NatContent(token_identifier),
+                ),
+            ],
+        ))
+    })
+}
+
+/// every item is validated before anything is minted, so the batch is minted entirely or not at all,
+/// one tx is recorded per item and their ids are returned as `[start, end)`
+#[cfg(feature = "mint")]
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+pub fn dip721_mint_batch(items: Vec<MintRequest>) -> Result<TxIdRange, NftError> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let mut token_identifiers = HashSet::new();
+        for item in items.iter() {
+            (ledger.is_token_existed(&item.token_identifier).not()
+                && token_identifiers.insert(item.token_identifier.clone()))
+            .then_some(())
+            .ok_or(NftError::ExistedNFT)?;
+        }
+        ledger
+            .is_within_max_supply(items.len())
+            .then_some(())
+            .ok_or(NftError::MaxSupplyReached)?;
+
+        let start = ledger.tx_count();
+        for (to, token_identifier) in ledger.mint_batch(caller, items)? {
+            ledger.update_owner_cache(&token_identifier, None, Some(to));
+            ledger.add_tx(
+                caller,
+                "mint".into(),
+                vec![
+                    ("to".into(), GenericValue::Principal(to)),
+                    (
+                        "token_identifier".into(),
+                        GenericValue::NatContent(token_identifier),
+                    ),
+                ],
+            );
+        }
+
+        Ok(TxIdRange {
+            start,
+            end: ledger.tx_count(),
+        })
+    })
+}
+
+#[cfg(feature = "burn")]
+#[update]
+#[candid_method(update)]
+pub fn dip721_burn(token_identifier: TokenIdentifier) -> Result<Nat, NftError> {
+    ledger::with_mut(|ledger| {
+        let caller = caller();
+        let old_owner = ledger.owner_of_default_subaccount(&token_identifier)?;
+        old_owner
+            .eq(&Some(caller))
+            .then_some(())
+            .ok_or(NftError::UnauthorizedOwner)?;
+        let old_operators = ledger.operators_of(&token_identifier)?;
+        ledger.burn(caller, &token_identifier)?;
+        ledger.update_owner_cache(&token_identifier, old_owner, None);
+        for old_operator in old_operators {
+            ledger.update_operator_cache(&token_identifier, Some(old_operator), None);
+        }
+
+        Ok(ledger.add_tx(
+            caller,
+            "burn".into(),
+            vec![(
+                "token_identifier".into(),
+                GenericValue::NatContent(token_identifier),
+            )],
+        ))
+    })
+}
+
+// ==================================================================================================
+// transfer and notify
+// ==================================================================================================
+/// Heuristic, canister ids are opaque ids whose last byte is `0x01`. There is no system api telling
+/// canisters apart, other opaque ids are notified too and count as rejecting.
+fn is_canister(principal: &Principal) -> bool {
+    principal.as_slice().last() == Some(&1)
+}
+
+/// Calls `on_dip721_received : (operator, from, token_identifier, data) -> (bool)` on canister recipients
+/// once the transfer is applied. Nothing is rolled back or held in escrow: an nft not accepted with `true`
+/// is transferred back to `from` in a second tx recording `rejected_tx_id`, and `TransferRejected` returned.
+/// `TransferNotReturned` is returned instead when `to` already moved the nft on or a hook vetoed the transfer back.
+///
+/// The call waits for the receiver's reply without a timeout, the nft belongs to `to` meanwhile and a
+/// receiver that never replies keeps the call pending.
+async fn notify_receiver(
+    operator: Principal,
+    from: Principal,
+    to: Principal,
+    token_identifier: TokenIdentifier,
+    data: Vec<u8>,
+    tx_id: Nat,
+) -> Result<Nat, NftError> {
+    if !is_canister(&to) {
+        return Ok(tx_id);
+    }
+    let reply: Result<(bool,), _> = ic_cdk::call(
+        to,
+        "on_dip721_received",
+        (operator, from, token_identifier.clone(), data),
+    )
+    .await;
+    if let Ok((true,)) = reply {
+        return Ok(tx_id);
+    }
+
+    ledger::with_mut(|ledger| match ledger.owner_account_of(&token_identifier) {
+        Ok(Some(owner)) if owner == Account::from(to) => transfer_between_accounts(
+            ledger,
+            operator,
+            "transfer",
+            owner,
+            from.into(),
+            token_identifier,
+            vec![("rejected_tx_id".into(), GenericValue::NatContent(tx_id))],
+        )
+        .map_or(Err(NftError::TransferNotReturned), |_| {
+            Err(NftError::TransferRejected)
+        }),
+        _ => Err(NftError::TransferNotReturned),
+    })
+}
+
+/// `dip721_transfer` notifying canister recipients, approvals of the nft don't survive a transfer back
+#[update]
+#[candid_method(update)]
+pub async fn dip721_transfer_and_notify(
+    to: Principal,
+    token_identifier: TokenIdentifier,
+    data: Vec<u8>,
+) -> Result<Nat, NftError> {
+    let caller = caller();
+    let tx_id = dip721_transfer(to, token_identifier.clone())?;
+    notify_receiver(caller, caller, to, token_identifier, data, tx_id).await
+}
+
+/// `dip721_transfer_from` notifying canister recipients, approvals of the nft don't survive a transfer back
+#[cfg(feature = "approval")]
+#[update]
+#[candid_method(update)]
+pub async fn dip721_transfer_from_and_notify(
+    owner: Principal,
+    to: Principal,
+    token_identifier: TokenIdentifier,
+    data: Vec<u8>,
+) -> Result<Nat, NftError> {
+    let tx_id = dip721_transfer_from(owner, to, token_identifier.clone())?;
+    notify_receiver(caller(), owner, to, token_identifier, data, tx_id).await
+}
+
+// ==================================================================================================
+// history
+// ==================================================================================================
+#[cfg(feature = "history")]
+#[query()]
+#[candid_method(query)]
+pub fn dip721_transaction(tx_id: Nat) -> Result<TxEvent, NftError> {
+    ledger::with(|ledger| ledger.tx(&tx_id))
+}
+
+/// Events touching `token_identifier`, oldest first, starting from tx id `cursor`.
+#[cfg(feature = "history")]
+#[query()]
+#[candid_method(query)]
+pub fn dip721_transactions_by_token(
+    token_identifier: TokenIdentifier,
+    cursor: Option<Nat>,
+    limit: u32,
+) -> TxEventPage {
+    ledger::with(|ledger| ledger.txs_by_token(&token_identifier, cursor, limit))
+}
+
+/// Events sent by `caller`, oldest first, starting from tx id `cursor`.
+#[cfg(feature = "history")]
+#[query()]
+#[candid_method(query)]
+pub fn dip721_transactions_by_caller(
+    caller: Principal,
+    cursor: Option<Nat>,
+    limit: u32,
+) -> TxEventPage {
+    ledger::with(|ledger| ledger.txs_by_caller(&caller, cursor, limit))
+}
+
+/// Events naming `counterparty` as `owner`, `to` or `operator`, oldest first, starting from tx id `cursor`.
+#[cfg(feature = "history")]
+#[query()]
+#[candid_method(query)]
+pub fn dip721_transactions_by_counterparty(
+    counterparty: Principal,
+    cursor: Option<Nat>,
+    limit: u32,
+) -> TxEventPage {
+    ledger::with(|ledger| ledger.txs_by_counterparty(&counterparty, cursor, limit))
+}
+
+// ==================================================================================================
+// approval expiry
+// ==================================================================================================
+/// NOTE:
+/// lapsed approvals are already hidden from every read,
+/// the sweep only clears them out of storage a bounded batch at a time,
+/// the timer is armed for the next approval to lapse and stays off while none is waiting,
+/// it also starts the deliveries to event subscribers and the cap outbox flush
+#[cfg_attr(feature = "lifecycle", export_name = "canister_global_timer")]
+pub fn global_timer() {
+    ic_cdk::setup();
+    timer::fired();
+    ledger::with_mut(|ledger| ledger.sweep_expired_approvals());
+    subscriptions::deliver();
+    cap::flush();
+}
+
+// ==================================================================================================
+// upgrade
+// ==================================================================================================
+/// NOTE:
+/// tokens, owners and operators already live in stable memory,
+/// only metadata, tx count and the cap root bucket being installed are saved here so upgrades don't grow
+/// with the collection, the state is tagged with its schema version for the migrations run in post_upgrade
+#[cfg_attr(feature = "lifecycle", pre_upgrade)]
+pub fn pre_upgrade() {
+    ledger::with_mut(|ledger| {
+        ledger.set_schema_version(migrations::VERSION);
+        if let Err(err) = ledger.save_heap_state(cap::created_bucket()) {
+            trap(&format!(
+                "An error occurred when saving to stable memory (pre_upgrade): {:?}",
+                err
+            ));
+        };
+    })
+}
+
+/// `args` switch the cap router like `dip721_set_cap`.
+#[cfg_attr(feature = "lifecycle", post_upgrade)]
+pub fn post_upgrade(args: Option<UpgradeArgs>) {
+    // canisters installed before the ledger moved into stable memory still hold a `stable_save` dump
+    let restored = if ledger::is_legacy_stable_memory() {
+        ic_cdk::storage::stable_restore::<(ledger::LegacyLedger, ledger::LegacyCapArchive)>().map(
+            |(legacy_ledger, legacy_cap)| {
+                ledger::with_mut(|ledger| ledger.restore_legacy(legacy_ledger, legacy_cap))
+            },
+        )
+    } else {
+        ledger::with_mut(|ledger| ledger.load_heap_state())
+    };
+    match restored {
+        Ok(created_bucket) => {
+            if let Err(err) = ledger::with_mut(migrations::migrate) {
+                trap(&format!(
+                    "An error occurred when migrating the stable state (post_upgrade): {}",
+                    err
+                ));
+            }
+            ledger::with_mut(|ledger| ledger.metadata_mut().upgraded_at = time());
+            // upgrades disarm the timer
+            ledger::with(|ledger| {
+                ledger.schedule_sweep();
+                subscriptions::schedule(ledger);
+                if ledger.cap_outbox_len() > 0 {
+                    cap::schedule();
+                }
+            });
+            // certified data doesn't survive upgrades
+            ledger::with(|ledger| icrc3::certify(ledger.tip()));
+            cap::restore_created_bucket(created_bucket);
+            if let Some(cap) = args.and_then(|args| args.cap) {
+                cap::set_router(Some(cap));
+            }
+        }
+        Err(err) => {
+            trap(&format!(
+                "An error occurred when loading from stable memory (post_upgrade): {:?}",
+                err
+            ));
+        }
+    }
+}
+
+/// Candid of every endpoint compiled in, embedding canisters included.
+#[query()]
+pub fn export_candid() -> String {
+    // the service refers to the types of the icrc, ext and v1 endpoints unqualified
+    #[cfg(feature = "ext")]
+    use ext::*;
+    use icrc3::*;
+    #[cfg(feature = "approval")]
+    use icrc37::*;
+    use icrc7::*;
+    #[cfg(feature = "v1")]
+    use v1::*;
+
+    ic_cdk::export::candid::export_service!();
+    __export_service()
+}
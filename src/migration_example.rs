@@ -90,6 +90,7 @@ fn post_upgrade_example() {
                     operators: Default::default(),
                     // consider import from cap or continue counting forward
                     // or even continue using from old cap root bucket
+                    // a new root bucket is filled back from the tx log with `dip721_resync_cap`
                     tx_records: vec![],
                 };
             });
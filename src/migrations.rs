@@ -0,0 +1,110 @@
+//! Versions of the stable state and the steps migrating each one to the next.
+//!
+//! `pre_upgrade` tags the state with [`VERSION`], `post_upgrade` runs the steps from the tagged version on.
+//! A change candid can't decode from the previous state, or a stored value the code now relies on,
+//! bumps `VERSION` and registers the step migrating the previous version. Stable structures are read
+//! with the current types, a change to a stored value or heap state type keeps the previous one to
+//! read it in its step.
+//!
+//! - 0: heap ledger dumped with `stable_save`, `post_upgrade` restores it into version 1 itself
+//! - 1: ledger in stable memory, states saved before the tag read as version 1
+
+use crate::*;
+use ledger::Ledger;
+
+pub const VERSION: u32 = 1;
+
+type Migration = fn(&mut Ledger) -> Result<(), String>;
+
+/// `(version, step)`, in order, each step migrates the state of `version` to the next one.
+const MIGRATIONS: &[(u32, Migration)] = &[];
+
+/// Runs every step from the tagged version on and tags the state with [`VERSION`].
+pub fn migrate(ledger: &mut Ledger) -> Result<(), String> {
+    run(ledger, MIGRATIONS, VERSION)
+}
+
+fn run(ledger: &mut Ledger, migrations: &[(u32, Migration)], version: u32) -> Result<(), String> {
+    let tagged_version = ledger.schema_version();
+    if tagged_version > version {
+        return Err(format!(
+            "state version {} is newer than {}, downgrades aren't supported",
+            tagged_version, version
+        ));
+    }
+    for (from, step) in migrations
+        .iter()
+        .filter(|(from, _)| *from >= tagged_version)
+    {
+        step(ledger).map_err(|err| format!("migrating version {}: {}", from, err))?;
+    }
+    ledger.set_schema_version(version);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // each step records its version in the collection name
+    fn record_step(ledger: &mut Ledger, version: u32) -> Result<(), String> {
+        let metadata = ledger.metadata_mut();
+        let name = metadata.name.get_or_insert_with(String::new);
+        name.push_str(&version.to_string());
+        Ok(())
+    }
+
+    const STEPS: &[(u32, Migration)] = &[
+        (1, |ledger| record_step(ledger, 1)),
+        (2, |ledger| record_step(ledger, 2)),
+        (3, |ledger| record_step(ledger, 3)),
+    ];
+
+    #[test]
+    fn migrate_tags_the_current_version() {
+        let mut ledger = Ledger::default();
+        assert_eq!(ledger.schema_version(), 1);
+        assert_eq!(migrate(&mut ledger), Ok(()));
+        assert_eq!(ledger.schema_version(), VERSION);
+    }
+
+    #[test]
+    fn run_steps_from_the_tagged_version_in_order() {
+        let mut ledger = Ledger::default();
+        ledger.set_schema_version(2);
+        assert_eq!(run(&mut ledger, STEPS, 4), Ok(()));
+        assert_eq!(ledger.metadata().name.as_deref(), Some("23"));
+        assert_eq!(ledger.schema_version(), 4);
+
+        // a tagged state has nothing left to migrate
+        assert_eq!(run(&mut ledger, STEPS, 4), Ok(()));
+        assert_eq!(ledger.metadata().name.as_deref(), Some("23"));
+    }
+
+    #[test]
+    fn run_stops_at_the_first_failing_step() {
+        let mut ledger = Ledger::default();
+        let steps: &[(u32, Migration)] = &[
+            (1, |ledger| record_step(ledger, 1)),
+            (2, |_| Err("corrupted".into())),
+            (3, |ledger| record_step(ledger, 3)),
+        ];
+        assert_eq!(
+            run(&mut ledger, steps, 4),
+            Err("migrating version 2: corrupted".into())
+        );
+        assert_eq!(ledger.metadata().name.as_deref(), Some("1"));
+        assert_eq!(ledger.schema_version(), 1);
+    }
+
+    #[test]
+    fn run_rejects_downgrades() {
+        let mut ledger = Ledger::default();
+        ledger.set_schema_version(5);
+        assert_eq!(
+            run(&mut ledger, STEPS, 4),
+            Err("state version 5 is newer than 4, downgrades aren't supported".into())
+        );
+        assert_eq!(ledger.schema_version(), 5);
+    }
+}
@@ -0,0 +1,160 @@
+//! Push delivery of ledger events to the canisters the custodians subscribed.
+//! Every subscriber has its own cursor, events are sent in batches to its
+//! `on_dip721_events : (vec record { nat; TxEvent }) -> ()` method at least once, retried with an exponential backoff,
+//! deliveries run from the global timer so a failing subscriber never holds up an update.
+
+use crate::*;
+
+const MAX_BATCH_SIZE: usize = 100;
+const MAX_SCAN_SIZE: usize = 1000; // txs looked at per delivery, matching or not
+const RETRY_DELAY: u64 = 10 * 1_000_000_000; // 10s in ns, doubled on every failure
+const MAX_RETRY_DELAY: u64 = 60 * 60 * 1_000_000_000; // 1h in ns
+
+thread_local!(
+    // deliveries awaiting a reply, not persisted since upgrades wait for them
+    static IN_FLIGHT: RefCell<HashSet<Principal>> = RefCell::default();
+);
+
+fn retry_delay(failures: u32) -> u64 {
+    RETRY_DELAY
+        .saturating_mul(1 << failures.saturating_sub(1).min(16))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Events from the subscriber cursor matching its event types, and the cursor to store once they are delivered.
+fn pending_events(ledger: &ledger::Ledger, subscriber: &Subscriber) -> (Vec<(Nat, TxEvent)>, Nat) {
+    let tx_count = ledger.tx_count();
+    let mut cursor = subscriber.cursor.clone();
+    let mut events = vec![];
+    for _ in 0..MAX_SCAN_SIZE {
+        if cursor >= tx_count || events.len() >= MAX_BATCH_SIZE {
+            break;
+        }
+        if let Ok(tx_event) = ledger.tx(&cursor) {
+            if EventType::of(&tx_event.operation)
+                .is_some_and(|event_type| subscriber.event_types.contains(&event_type))
+            {
+                events.push((cursor.clone(), tx_event));
+            }
+        }
+        cursor += 1;
+    }
+    (events, cursor)
+}
+
+/// Arms the timer for every subscriber with txs left to look at that isn't waiting on a delivery,
+/// at once or when its retry is due.
+pub fn schedule(ledger: &ledger::Ledger) {
+    let tx_count = ledger.tx_count();
+    for (principal, subscriber) in ledger.subscribers() {
+        if subscriber.cursor < tx_count
+            && !IN_FLIGHT.with(|in_flight| in_flight.borrow().contains(&principal))
+        {
+            timer::schedule(subscriber.retry_at.max(time()));
+        }
+    }
+}
+
+/// Starts a delivery to every due subscriber that isn't already waiting on one.
+pub fn deliver() {
+    let now = time();
+    let deliveries: Vec<_> = ledger::with_mut(|ledger| {
+        ledger
+            .subscribers()
+            .into_iter()
+            .filter(|(principal, subscriber)| {
+                subscriber.retry_at <= now
+                    && !IN_FLIGHT.with(|in_flight| in_flight.borrow().contains(principal))
+            })
+            .filter_map(|(principal, subscriber)| {
+                let (events, cursor) = pending_events(ledger, &subscriber);
+                if events.is_empty() {
+                    // nothing subscribed to in the scanned txs
+                    if cursor != subscriber.cursor {
+                        ledger.set_subscriber(
+                            principal,
+                            Subscriber {
+                                cursor,
+                                ..subscriber
+                            },
+                        );
+                    }
+                    return None;
+                }
+                Some((principal, events, cursor))
+            })
+            .collect()
+    });
+    for (principal, events, cursor) in deliveries {
+        IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().insert(principal));
+        ic_cdk::spawn(send(principal, events, cursor));
+    }
+    // subscribers whose scan stopped short of the log or whose retry isn't due yet
+    ledger::with(schedule);
+}
+
+async fn send(principal: Principal, events: Vec<(Nat, TxEvent)>, cursor: Nat) {
+    let result: Result<(), _> = ic_cdk::call(principal, "on_dip721_events", (events,)).await;
+    IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().remove(&principal));
+    ledger::with_mut(|ledger| {
+        // unsubscribed meanwhile
+        let mut subscriber = match ledger.subscriber(&principal) {
+            Some(subscriber) => subscriber,
+            None => return,
+        };
+        match result {
+            Ok(()) => {
+                subscriber.cursor = cursor;
+                subscriber.failures = 0;
+                subscriber.retry_at = 0;
+            }
+            Err(_) => {
+                subscriber.failures = subscriber.failures.saturating_add(1);
+                subscriber.retry_at = time().saturating_add(retry_delay(subscriber.failures));
+            }
+        }
+        ledger.set_subscriber(principal, subscriber);
+        schedule(ledger);
+    });
+}
+
+// ==================================================================================================
+// subscriptions
+// ==================================================================================================
+/// Subscribes `subscriber` to the events of `event_types` recorded from now on,
+/// an existing subscriber only has its event types replaced.
+/// Events are read from the tx log, subscribing is rejected when the history backend disables it.
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+fn dip721_subscribe(subscriber: Principal, event_types: Vec<EventType>) {
+    ledger::with_mut(|ledger| {
+        if !ledger.history().logs() {
+            trap("events are read from the tx log, which the history backend disables");
+        }
+        let state = match ledger.subscriber(&subscriber) {
+            Some(state) => Subscriber {
+                event_types,
+                ..state
+            },
+            None => Subscriber {
+                event_types,
+                cursor: ledger.tx_count(),
+                failures: 0,
+                retry_at: 0,
+            },
+        };
+        ledger.set_subscriber(subscriber, state);
+    })
+}
+
+#[update(guard = "is_canister_custodian")]
+#[candid_method(update)]
+fn dip721_unsubscribe(subscriber: Principal) {
+    ledger::with_mut(|ledger| ledger.remove_subscriber(&subscriber))
+}
+
+#[query()]
+#[candid_method(query)]
+fn dip721_subscribers() -> Vec<(Principal, Subscriber)> {
+    ledger::with(|ledger| ledger.subscribers())
+}
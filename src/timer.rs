@@ -0,0 +1,44 @@
+//! The canister global timer, armed only while some work is waiting so idle canisters don't pay for
+//! every round like they would with a heartbeat. It fires once, whatever ran in it arms it again
+//! for the work left.
+
+use std::cell::Cell;
+
+thread_local!(
+    // time the timer fires at, 0 while it isn't armed
+    static ARMED_AT: Cell<u64> = const { Cell::new(0) };
+);
+
+#[cfg(target_arch = "wasm32")]
+#[link(wasm_import_module = "ic0")]
+extern "C" {
+    fn global_timer_set(timestamp: i64) -> i64;
+}
+
+#[cfg(target_arch = "wasm32")]
+fn set(timestamp: u64) {
+    unsafe {
+        global_timer_set(timestamp as i64);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn set(_timestamp: u64) {}
+
+/// Arms the timer for `at` in nanoseconds since the epoch, unless it already fires earlier.
+/// Times in the past fire in the next round.
+pub fn schedule(at: u64) {
+    // 0 would deactivate the timer
+    let at = at.max(1);
+    ARMED_AT.with(|armed_at| {
+        if armed_at.get() == 0 || at < armed_at.get() {
+            armed_at.set(at);
+            set(at);
+        }
+    });
+}
+
+/// Must be called first when the timer fires, the system disarms it then.
+pub fn fired() {
+    ARMED_AT.with(|armed_at| armed_at.set(0));
+}
@@ -0,0 +1,375 @@
+//! Deprecated DIP721 v1 methods, see the "Deprecated Interface & Data Structure" section of `spec.md`.
+//! Token ids are nat64 in v1, nfts whose identifier doesn't fit are left out of the v1 responses.
+
+use crate::*;
+use num_traits::ToPrimitive;
+
+#[derive(CandidType)]
+pub enum ApiError {
+    Unauthorized,
+    InvalidTokenId,
+    ZeroAddress,
+    Other,
+}
+
+impl From<NftError> for ApiError {
+    fn from(error: NftError) -> Self {
+        match error {
+            NftError::UnauthorizedOwner | NftError::UnauthorizedOperator => ApiError::Unauthorized,
+            NftError::TokenNotFound => ApiError::InvalidTokenId,
+            NftError::OwnerNotFound => ApiError::ZeroAddress,
+            _ => ApiError::Other,
+        }
+    }
+}
+
+#[derive(CandidType)]
+pub enum OwnerResult {
+    Ok(Principal),
+    Err(ApiError),
+}
+
+#[cfg(feature = "approval")]
+#[derive(CandidType)]
+pub enum ApproveResult {
+    Ok(Nat),
+    Err(ApiError),
+}
+
+#[cfg(feature = "approval")]
+#[derive(CandidType)]
+pub enum TxReceipt {
+    Ok(Nat),
+    Err(ApiError),
+}
+
+#[derive(CandidType)]
+pub enum InterfaceId {
+    Approval,
+    TransactionHistory,
+    Mint,
+    Burn,
+    #[allow(dead_code)] // part of the v1 interface, notifications aren't supported
+    TransferNotification,
+}
+
+#[derive(CandidType)]
+pub struct LogoResult {
+    pub logo_type: String, // MIME type of the logo
+    pub data: String,      // base64 encoded logo
+}
+
+pub type MetadataDesc = Vec<MetadataPart>;
+
+#[derive(CandidType, Deserialize)]
+pub struct MetadataPart {
+    pub purpose: MetadataPurpose,
+    pub key_val_data: Vec<MetadataKeyVal>,
+    pub data: Vec<u8>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum MetadataPurpose {
+    Preview,
+    Rendered,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct MetadataKeyVal {
+    pub key: String,
+    pub val: MetadataVal,
+}
+
+#[allow(clippy::enum_variant_names)] // v1 names
+#[derive(CandidType, Deserialize)]
+pub enum MetadataVal {
+    TextContent(String),
+    BlobContent(Vec<u8>),
+    NatContent(Nat),
+    Nat8Content(u8),
+    Nat16Content(u16),
+    Nat32Content(u32),
+    Nat64Content(u64),
+}
+
+#[derive(CandidType)]
+pub enum MetadataResult {
+    Ok(MetadataDesc),
+    Err(ApiError),
+}
+
+#[derive(CandidType)]
+pub struct ExtendedMetadataResult {
+    pub metadata_desc: MetadataDesc,
+    pub token_id: u64,
+}
+
+#[cfg(feature = "mint")]
+#[derive(CandidType)]
+pub enum MintError {
+    Unauthorized,
+}
+
+#[cfg(feature = "mint")]
+#[derive(CandidType)]
+pub struct MintReceiptPart {
+    pub token_id: u64, // minted token id
+    pub id: Nat,       // transaction id
+}
+
+#[cfg(feature = "mint")]
+#[derive(CandidType)]
+pub enum MintReceipt {
+    Ok(MintReceiptPart),
+    Err(MintError),
+}
+
+#[cfg(feature = "approval")]
+fn tx_receipt(result: Result<Nat, NftError>) -> TxReceipt {
+    match result {
+        Ok(tx_id) => TxReceipt::Ok(tx_id),
+        Err(error) => TxReceipt::Err(error.into()),
+    }
+}
+
+/// Properties v1 can't represent (booleans, signed and nested values, ...) are left out.
+fn metadata_desc(properties: Vec<(String, GenericValue)>) -> MetadataDesc {
+    let key_val_data = properties
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let val = match value {
+                GenericValue::TextContent(value) => MetadataVal::TextContent(value),
+                GenericValue::BlobContent(value) => MetadataVal::BlobContent(value),
+                GenericValue::Principal(value) => MetadataVal::TextContent(value.to_text()),
+                GenericValue::NatContent(value) => MetadataVal::NatContent(value),
+                GenericValue::Nat8Content(value) => MetadataVal::Nat8Content(value),
+                GenericValue::Nat16Content(value) => MetadataVal::Nat16Content(value),
+                GenericValue::Nat32Content(value) => MetadataVal::Nat32Content(value),
+                GenericValue::Nat64Content(value) => MetadataVal::Nat64Content(value),
+                _ => return None,
+            };
+            Some(MetadataKeyVal { key, val })
+        })
+        .collect();
+    vec![MetadataPart {
+        purpose: MetadataPurpose::Rendered,
+        key_val_data,
+        data: vec![],
+    }]
+}
+
+#[cfg(feature = "mint")]
+fn properties(metadata_desc: MetadataDesc) -> Vec<(String, GenericValue)> {
+    metadata_desc
+        .into_iter()
+        .flat_map(|part| part.key_val_data)
+        .map(|pair| {
+            let value = match pair.val {
+                MetadataVal::TextContent(value) => GenericValue::TextContent(value),
+                MetadataVal::BlobContent(value) => GenericValue::BlobContent(value),
+                MetadataVal::NatContent(value) => GenericValue::NatContent(value),
+                MetadataVal::Nat8Content(value) => GenericValue::Nat8Content(value),
+                MetadataVal::Nat16Content(value) => GenericValue::Nat16Content(value),
+                MetadataVal::Nat32Content(value) => GenericValue::Nat32Content(value),
+                MetadataVal::Nat64Content(value) => GenericValue::Nat64Content(value),
+            };
+            (pair.key, value)
+        })
+        .collect()
+}
+
+fn token_ids_of(ledger: &ledger::Ledger, user: &Principal) -> Vec<u64> {
+    let mut token_ids: Vec<_> = ledger
+        .owner_token_identifiers(user)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|token_identifier| token_identifier.0.to_u64())
+        .collect();
+    token_ids.sort_unstable();
+    token_ids
+}
+
+// ==================================================================================================
+// v1 metadata
+// ==================================================================================================
+#[query(name = "nameDip721")]
+#[candid_method(query, rename = "nameDip721")]
+fn name_dip721() -> String {
+    ledger::with(|ledger| ledger.metadata().name.clone().unwrap_or_default())
+}
+
+#[query(name = "symbolDip721")]
+#[candid_method(query, rename = "symbolDip721")]
+fn symbol_dip721() -> String {
+    ledger::with(|ledger| ledger.metadata().symbol.clone().unwrap_or_default())
+}
+
+/// Data URI logos are split into their MIME type and base64 data, other logos are returned as is.
+#[query(name = "logoDip721")]
+#[candid_method(query, rename = "logoDip721")]
+fn logo_dip721() -> LogoResult {
+    ledger::with(|ledger| {
+        let logo = ledger.metadata().logo.clone().unwrap_or_default();
+        match logo
+            .strip_prefix("data:")
+            .and_then(|logo| logo.split_once(";base64,"))
+        {
+            Some((logo_type, data)) => LogoResult {
+                logo_type: logo_type.into(),
+                data: data.into(),
+            },
+            None => LogoResult {
+                logo_type: String::new(),
+                data: logo,
+            },
+        }
+    })
+}
+
+#[query(name = "totalSupplyDip721")]
+#[candid_method(query, rename = "totalSupplyDip721")]
+fn total_supply_dip721() -> u64 {
+    ledger::with(|ledger| ledger.tokens_count() as u64)
+}
+
+/// The max supply, saturated to nat16, `65535` when the supply isn't capped.
+#[query(name = "getMaxLimitDip721")]
+#[candid_method(query, rename = "getMaxLimitDip721")]
+fn get_max_limit_dip721() -> u16 {
+    ledger::with(|ledger| {
+        ledger
+            .metadata()
+            .max_supply
+            .as_ref()
+            .and_then(|max_supply| max_supply.0.to_u16())
+            .unwrap_or(u16::MAX)
+    })
+}
+
+#[query(name = "supportedInterfacesDip721")]
+#[candid_method(query, rename = "supportedInterfacesDip721")]
+fn supported_interfaces_dip721() -> Vec<InterfaceId> {
+    dip721_supported_interfaces()
+        .into_iter()
+        .map(|interface| match interface {
+            SupportedInterface::Approval => InterfaceId::Approval,
+            SupportedInterface::Mint => InterfaceId::Mint,
+            SupportedInterface::Burn => InterfaceId::Burn,
+            SupportedInterface::TransactionHistory => InterfaceId::TransactionHistory,
+        })
+        .collect()
+}
+
+// ==================================================================================================
+// v1 ownership
+// ==================================================================================================
+#[query(name = "balanceOfDip721")]
+#[candid_method(query, rename = "balanceOfDip721")]
+fn balance_of_dip721(user: Principal) -> u64 {
+    ledger::with(|ledger| token_ids_of(ledger, &user).len() as u64)
+}
+
+/// Burned nfts have no owner, they are reported as sent to the zero address.
+#[query(name = "ownerOfDip721")]
+#[candid_method(query, rename = "ownerOfDip721")]
+fn owner_of_dip721(token_id: u64) -> OwnerResult {
+    match dip721_owner_of(Nat::from(token_id)) {
+        Ok(Some(owner)) => OwnerResult::Ok(owner),
+        Ok(None) => OwnerResult::Err(ApiError::ZeroAddress),
+        Err(error) => OwnerResult::Err(error.into()),
+    }
+}
+
+#[query(name = "getTokenIdsForUserDip721")]
+#[candid_method(query, rename = "getTokenIdsForUserDip721")]
+fn get_token_ids_for_user_dip721(user: Principal) -> Vec<u64> {
+    ledger::with(|ledger| token_ids_of(ledger, &user))
+}
+
+#[query(name = "getMetadataDip721")]
+#[candid_method(query, rename = "getMetadataDip721")]
+fn get_metadata_dip721(token_id: u64) -> MetadataResult {
+    ledger::with(|ledger| match ledger.token_metadata(&Nat::from(token_id)) {
+        Ok(token_metadata) => MetadataResult::Ok(metadata_desc(token_metadata.properties)),
+        Err(error) => MetadataResult::Err(error.into()),
+    })
+}
+
+#[update(name = "getMetadataForUserDip721")]
+#[candid_method(update, rename = "getMetadataForUserDip721")]
+fn get_metadata_for_user_dip721(user: Principal) -> Vec<ExtendedMetadataResult> {
+    ledger::with(|ledger| {
+        token_ids_of(ledger, &user)
+            .into_iter()
+            .filter_map(|token_id| {
+                let token_metadata = ledger.token_metadata(&Nat::from(token_id)).ok()?;
+                Some(ExtendedMetadataResult {
+                    metadata_desc: metadata_desc(token_metadata.properties),
+                    token_id,
+                })
+            })
+            .collect()
+    })
+}
+
+// ==================================================================================================
+// v1 core api
+// ==================================================================================================
+#[cfg(feature = "approval")]
+#[update(name = "approveDip721")]
+#[candid_method(update, rename = "approveDip721")]
+fn approve_dip721(spender: Principal, token_id: u64) -> ApproveResult {
+    match dip721_approve(spender, Nat::from(token_id), None) {
+        Ok(tx_id) => ApproveResult::Ok(tx_id),
+        Err(error) => ApproveResult::Err(error.into()),
+    }
+}
+
+/// v1 lets the owner move its own nft with `transferFromDip721`.
+#[cfg(feature = "approval")]
+#[update(name = "transferFromDip721")]
+#[candid_method(update, rename = "transferFromDip721")]
+fn transfer_from_dip721(from: Principal, to: Principal, token_id: u64) -> TxReceipt {
+    if from == caller() {
+        tx_receipt(dip721_transfer(to, Nat::from(token_id)))
+    } else {
+        tx_receipt(dip721_transfer_from(from, to, Nat::from(token_id)))
+    }
+}
+
+/// Same as `transferFromDip721` but rejects the anonymous principal, v1's zero address.
+#[cfg(feature = "approval")]
+#[update(name = "safeTransferFromDip721")]
+#[candid_method(update, rename = "safeTransferFromDip721")]
+fn safe_transfer_from_dip721(from: Principal, to: Principal, token_id: u64) -> TxReceipt {
+    if to == Principal::anonymous() {
+        return TxReceipt::Err(ApiError::ZeroAddress);
+    }
+    transfer_from_dip721(from, to, token_id)
+}
+
+/// Mints the next sequential token id, the metadata parts are flattened into the nft properties.
+#[cfg(feature = "mint")]
+#[update(name = "mintDip721")]
+#[candid_method(update, rename = "mintDip721")]
+fn mint_dip721(to: Principal, metadata: MetadataDesc) -> MintReceipt {
+    if is_canister_custodian().is_err() {
+        return MintReceipt::Err(MintError::Unauthorized);
+    }
+    let token_identifier = ledger::with(|ledger| ledger.next_token_identifier());
+    let token_id = token_identifier
+        .0
+        .to_u64()
+        .unwrap_or_else(|| trap("Next token id doesn't fit in nat64"));
+    match dip721_mint(to, token_identifier, properties(metadata)) {
+        Ok(id) => MintReceipt::Ok(MintReceiptPart { token_id, id }),
+        // v1 only has `Unauthorized`, the others reject the call with their own message
+        Err(NftError::MaxSupplyReached) => trap("Max supply reached"),
+        Err(NftError::ExistedNFT) => trap("Next token id is already minted"),
+        Err(NftError::Other(message)) => trap(&message),
+        Err(NftError::UnauthorizedOwner | NftError::UnauthorizedOperator) => {
+            MintReceipt::Err(MintError::Unauthorized)
+        }
+        Err(_) => trap("Mint failed"),
+    }
+}
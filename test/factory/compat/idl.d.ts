@@ -0,0 +1,598 @@
+import type { Principal } from '@dfinity/principal';
+import type { ActorMethod } from '@dfinity/agent';
+
+export interface Account {
+  'owner' : Principal,
+  'subaccount' : [] | [Array<number>],
+}
+export type ApiError = { 'ZeroAddress' : null } |
+  { 'InvalidTokenId' : null } |
+  { 'Unauthorized' : null } |
+  { 'Other' : null };
+export interface ApprovalInfo {
+  'memo' : [] | [Array<number>],
+  'from_subaccount' : [] | [Array<number>],
+  'created_at_time' : bigint,
+  'expires_at' : [] | [bigint],
+  'spender' : Account,
+}
+export interface ApproveCollectionArg { 'approval_info' : ApprovalInfo }
+export type ApproveCollectionError = {
+    'GenericError' : { 'message' : string, 'error_code' : bigint }
+  } |
+  { 'TooOld' : null } |
+  { 'CreatedInFuture' : { 'ledger_time' : bigint } } |
+  { 'InvalidSpender' : null } |
+  {
+    'GenericBatchError' : { 'message' : string, 'error_code' : bigint }
+  };
+export type ApproveResult = { 'Ok' : bigint } |
+  { 'Err' : ApiError };
+export interface ApproveTokenArg {
+  'token_id' : bigint,
+  'approval_info' : ApprovalInfo,
+}
+export type ApproveTokenError = {
+    'GenericError' : { 'message' : string, 'error_code' : bigint }
+  } |
+  { 'TooOld' : null } |
+  { 'NonExistingTokenId' : null } |
+  { 'Unauthorized' : null } |
+  { 'CreatedInFuture' : { 'ledger_time' : bigint } } |
+  { 'InvalidSpender' : null } |
+  {
+    'GenericBatchError' : { 'message' : string, 'error_code' : bigint }
+  };
+export interface ArchivedBlocks {
+  'args' : Array<GetBlocksArgs>,
+  'callback' : [Principal, string],
+}
+export interface BlockWithId { 'id' : bigint, 'block' : Value }
+export interface CapOutboxStats {
+  'pending' : bigint,
+  'failed' : number,
+  'retry_at' : [] | [bigint],
+  'last_error' : [] | [string],
+  'resync_cursor' : [] | [bigint],
+}
+export type CommonError = { 'InvalidToken' : string } |
+  { 'Other' : string };
+export interface DataCertificate {
+  'certificate' : Array<number>,
+  'hash_tree' : Array<number>,
+}
+export type EventType = { 'Mint' : null } |
+  { 'Transfer' : null } |
+  { 'Burn' : null } |
+  { 'Approve' : null };
+export type ExtResult = { 'ok' : string } |
+  { 'err' : CommonError };
+export type ExtResult_1 = {
+    'ok' : Array<[number, [] | [Listing], [] | [Array<number>]]>
+  } |
+  { 'err' : CommonError };
+export type ExtTransferError = { 'CannotNotify' : string } |
+  { 'InsufficientBalance' : null } |
+  { 'InvalidToken' : string } |
+  { 'Rejected' : null } |
+  { 'Unauthorized' : string } |
+  { 'Other' : string };
+export interface ExtTransferRequest {
+  'to' : User,
+  'token' : string,
+  'notify' : boolean,
+  'from' : User,
+  'memo' : Array<number>,
+  'subaccount' : [] | [Array<number>],
+  'amount' : bigint,
+}
+export type ExtTransferResponse = { 'ok' : bigint } |
+  { 'err' : ExtTransferError };
+export interface ExtendedMetadataResult {
+  'token_id' : bigint,
+  'metadata_desc' : Array<MetadataPart>,
+}
+export type GenericValue = { 'Nat64Content' : bigint } |
+  { 'Nat32Content' : number } |
+  { 'BoolContent' : boolean } |
+  { 'Nat8Content' : number } |
+  { 'Int64Content' : bigint } |
+  { 'IntContent' : bigint } |
+  { 'NatContent' : bigint } |
+  { 'Nat16Content' : number } |
+  { 'Int32Content' : number } |
+  { 'Int8Content' : number } |
+  { 'FloatContent' : number } |
+  { 'Int16Content' : number } |
+  { 'BlobContent' : Array<number> } |
+  { 'NestedContent' : Vec } |
+  { 'Principal' : Principal } |
+  { 'TextContent' : string };
+export interface GetBlocksArgs { 'start' : bigint, 'length' : bigint }
+export interface GetBlocksResult {
+  'log_length' : bigint,
+  'blocks' : Array<BlockWithId>,
+  'archived_blocks' : Array<ArchivedBlocks>,
+}
+export type HistoryBackend = { 'Cap' : null } |
+  { 'Log' : null } |
+  { 'Both' : null } |
+  { 'Disabled' : null };
+export interface InitArgs {
+  'cap' : [] | [Principal],
+  'logo' : [] | [string],
+  'name' : [] | [string],
+  'custodians' : [] | [Array<Principal>],
+  'symbol' : [] | [string],
+  'history' : [] | [HistoryBackend],
+  'max_supply' : [] | [bigint],
+}
+export type InterfaceId = { 'Burn' : null } |
+  { 'Mint' : null } |
+  { 'Approval' : null } |
+  { 'TransactionHistory' : null } |
+  { 'TransferNotification' : null };
+export interface IsApprovedArg {
+  'token_id' : bigint,
+  'from_subaccount' : [] | [Array<number>],
+  'spender' : Account,
+}
+export interface Listing {
+  'locked' : [] | [bigint],
+  'seller' : Principal,
+  'price' : bigint,
+}
+export interface LogoResult { 'data' : string, 'logo_type' : string }
+export interface ManualReply {
+  'cap' : [] | [Principal],
+  'logo' : [] | [string],
+  'name' : [] | [string],
+  'created_at' : bigint,
+  'upgraded_at' : bigint,
+  'custodians' : Array<Principal>,
+  'symbol' : [] | [string],
+  'history' : HistoryBackend,
+  'max_supply' : [] | [bigint],
+}
+export type ManualReply_1 = { 'Ok' : Array<bigint> } |
+  { 'Err' : NftError };
+export type ManualReply_2 = { 'Ok' : Array<TokenMetadata> } |
+  { 'Err' : NftError };
+export type ManualReply_3 = { 'Ok' : TokenMetadata } |
+  { 'Err' : NftError };
+export interface MetadataKeyVal { 'key' : string, 'val' : MetadataVal }
+export interface MetadataPart {
+  'data' : Array<number>,
+  'key_val_data' : Array<MetadataKeyVal>,
+  'purpose' : MetadataPurpose,
+}
+export type MetadataPurpose = { 'Preview' : null } |
+  { 'Rendered' : null };
+export type MetadataResult = { 'Ok' : Array<MetadataPart> } |
+  { 'Err' : ApiError };
+export type MetadataVal = { 'Nat64Content' : bigint } |
+  { 'Nat32Content' : number } |
+  { 'Nat8Content' : number } |
+  { 'NatContent' : bigint } |
+  { 'Nat16Content' : number } |
+  { 'BlobContent' : Array<number> } |
+  { 'TextContent' : string };
+export type MintError = { 'Unauthorized' : null };
+export type MintReceipt = { 'Ok' : MintReceiptPart } |
+  { 'Err' : MintError };
+export interface MintReceiptPart { 'id' : bigint, 'token_id' : bigint }
+export interface MintRequest {
+  'to' : Principal,
+  'token_identifier' : bigint,
+  'properties' : Array<[string, GenericValue]>,
+}
+export type NftError = { 'UnauthorizedOperator' : null } |
+  { 'SelfTransfer' : null } |
+  { 'TokenNotFound' : null } |
+  { 'UnauthorizedOwner' : null } |
+  { 'SelfApprove' : null } |
+  { 'OperatorNotFound' : null } |
+  { 'ExistedNFT' : null } |
+  { 'OwnerNotFound' : null } |
+  { 'TxNotFound' : null } |
+  { 'InvalidExpiry' : null } |
+  { 'MaxSupplyReached' : null } |
+  { 'InvalidSubaccount' : null } |
+  { 'Other' : string } |
+  { 'TransferRejected' : null } |
+  { 'TransferNotReturned' : null };
+export type OwnerResult = { 'Ok' : Principal } |
+  { 'Err' : ApiError };
+export type Result = { 'Ok' : bigint } |
+  { 'Err' : NftError };
+export type Result_1 = { 'Ok' : boolean } |
+  { 'Err' : NftError };
+export type Result_10 = { 'Ok' : bigint } |
+  { 'Err' : RevokeCollectionApprovalError };
+export type Result_11 = { 'Ok' : bigint } |
+  { 'Err' : RevokeTokenApprovalError };
+export type Result_12 = { 'Ok' : bigint } |
+  { 'Err' : TransferFromError };
+export type Result_2 = { 'Ok' : [] | [Principal] } |
+  { 'Err' : NftError };
+export type Result_3 = { 'Ok' : Array<Principal> } |
+  { 'Err' : NftError };
+export type Result_4 = { 'Ok' : TxEvent } |
+  { 'Err' : NftError };
+export type Result_5 = { 'Ok' : TxIdRange } |
+  { 'Err' : NftError };
+export type Result_6 = { 'Ok' : [] | [Account] } |
+  { 'Err' : NftError };
+export type Result_7 = { 'Ok' : bigint } |
+  { 'Err' : TransferError };
+export type Result_8 = { 'Ok' : bigint } |
+  { 'Err' : ApproveCollectionError };
+export type Result_9 = { 'Ok' : bigint } |
+  { 'Err' : ApproveTokenError };
+export interface RevokeCollectionApprovalArg {
+  'memo' : [] | [Array<number>],
+  'from_subaccount' : [] | [Array<number>],
+  'created_at_time' : [] | [bigint],
+  'spender' : [] | [Account],
+}
+export type RevokeCollectionApprovalError = {
+    'GenericError' : { 'message' : string, 'error_code' : bigint }
+  } |
+  { 'TooOld' : null } |
+  { 'CreatedInFuture' : { 'ledger_time' : bigint } } |
+  { 'ApprovalDoesNotExist' : null } |
+  {
+    'GenericBatchError' : { 'message' : string, 'error_code' : bigint }
+  };
+export interface RevokeTokenApprovalArg {
+  'token_id' : bigint,
+  'memo' : [] | [Array<number>],
+  'from_subaccount' : [] | [Array<number>],
+  'created_at_time' : [] | [bigint],
+  'spender' : [] | [Account],
+}
+export type RevokeTokenApprovalError = {
+    'GenericError' : { 'message' : string, 'error_code' : bigint }
+  } |
+  { 'TooOld' : null } |
+  { 'NonExistingTokenId' : null } |
+  { 'Unauthorized' : null } |
+  { 'CreatedInFuture' : { 'ledger_time' : bigint } } |
+  { 'ApprovalDoesNotExist' : null } |
+  {
+    'GenericBatchError' : { 'message' : string, 'error_code' : bigint }
+  };
+export interface Standard { 'url' : string, 'name' : string }
+export interface Stats {
+  'cycles' : bigint,
+  'total_transactions' : bigint,
+  'total_unique_holders' : bigint,
+  'total_supply' : bigint,
+}
+export interface Subscriber {
+  'event_types' : Array<EventType>,
+  'cursor' : bigint,
+  'failures' : number,
+  'retry_at' : bigint,
+}
+export interface SupportedBlockType { 'url' : string, 'block_type' : string }
+export type SupportedInterface = { 'Burn' : null } |
+  { 'Mint' : null } |
+  { 'Approval' : null } |
+  { 'TransactionHistory' : null };
+export interface TokenApproval {
+  'token_id' : bigint,
+  'approval_info' : ApprovalInfo,
+}
+export interface TokenMetadata {
+  'transferred_at' : [] | [bigint],
+  'transferred_by' : [] | [Principal],
+  'owner' : [] | [Principal],
+  'owner_subaccount' : [] | [Array<number>],
+  'operator' : [] | [Principal],
+  'operators' : Array<Principal>,
+  'approved_at' : [] | [bigint],
+  'approved_by' : [] | [Principal],
+  'properties' : Array<[string, GenericValue]>,
+  'is_burned' : boolean,
+  'token_identifier' : bigint,
+  'burned_at' : [] | [bigint],
+  'burned_by' : [] | [Principal],
+  'minted_at' : bigint,
+  'minted_by' : Principal,
+}
+export interface TransferArg {
+  'to' : Account,
+  'token_id' : bigint,
+  'memo' : [] | [Array<number>],
+  'from_subaccount' : [] | [Array<number>],
+  'created_at_time' : [] | [bigint],
+}
+export type TransferError = {
+    'GenericError' : { 'message' : string, 'error_code' : bigint }
+  } |
+  { 'TooOld' : null } |
+  { 'InvalidRecipient' : null } |
+  { 'NonExistingTokenId' : null } |
+  { 'Unauthorized' : null } |
+  { 'CreatedInFuture' : { 'ledger_time' : bigint } } |
+  { 'Duplicate' : { 'duplicate_of' : bigint } } |
+  {
+    'GenericBatchError' : { 'message' : string, 'error_code' : bigint }
+  };
+export interface TransferFromArg {
+  'to' : Account,
+  'spender_subaccount' : [] | [Array<number>],
+  'token_id' : bigint,
+  'from' : Account,
+  'memo' : [] | [Array<number>],
+  'created_at_time' : [] | [bigint],
+}
+export type TransferFromError = {
+    'GenericError' : { 'message' : string, 'error_code' : bigint }
+  } |
+  { 'TooOld' : null } |
+  { 'InvalidRecipient' : null } |
+  { 'NonExistingTokenId' : null } |
+  { 'Unauthorized' : null } |
+  { 'CreatedInFuture' : { 'ledger_time' : bigint } } |
+  { 'Duplicate' : { 'duplicate_of' : bigint } } |
+  {
+    'GenericBatchError' : { 'message' : string, 'error_code' : bigint }
+  };
+export interface TransferFromRequest {
+  'to' : Principal,
+  'token_identifier' : bigint,
+  'owner' : Principal,
+}
+export interface TransferRequest { 'to' : Principal, 'token_identifier' : bigint }
+export interface TxEvent {
+  'time' : bigint,
+  'operation' : string,
+  'details' : Array<[string, GenericValue]>,
+  'caller' : Principal,
+}
+export interface TxEventPage {
+  'events' : Array<[bigint, TxEvent]>,
+  'next_cursor' : [] | [bigint],
+}
+export interface TxIdRange { 'end' : bigint, 'start' : bigint }
+export type TxReceipt = { 'Ok' : bigint } |
+  { 'Err' : ApiError };
+export type User = { 'principal' : Principal } |
+  { 'address' : string };
+export type Value = { 'Int' : bigint } |
+  { 'Map' : Array<[string, Value]> } |
+  { 'Nat' : bigint } |
+  { 'Blob' : Array<number> } |
+  { 'Text' : string } |
+  { 'Array' : Array<Value> };
+export type Vec = Array<
+  [
+    string,
+    { 'Nat64Content' : bigint } |
+      { 'Nat32Content' : number } |
+      { 'BoolContent' : boolean } |
+      { 'Nat8Content' : number } |
+      { 'Int64Content' : bigint } |
+      { 'IntContent' : bigint } |
+      { 'NatContent' : bigint } |
+      { 'Nat16Content' : number } |
+      { 'Int32Content' : number } |
+      { 'Int8Content' : number } |
+      { 'FloatContent' : number } |
+      { 'Int16Content' : number } |
+      { 'BlobContent' : Array<number> } |
+      { 'NestedContent' : Vec } |
+      { 'Principal' : Principal } |
+      { 'TextContent' : string },
+  ]
+>;
+export interface _SERVICE {
+  'approve' : ActorMethod<[Principal, bigint], Result>,
+  'approveDip721' : ActorMethod<[Principal, bigint], ApproveResult>,
+  'balanceOf' : ActorMethod<[Principal], Result>,
+  'balanceOfDip721' : ActorMethod<[Principal], bigint>,
+  'bearer' : ActorMethod<[string], ExtResult>,
+  'burn' : ActorMethod<[bigint], Result>,
+  'custodians' : ActorMethod<[], Array<Principal>>,
+  'cycles' : ActorMethod<[], bigint>,
+  'dfx_info' : ActorMethod<[], string>,
+  'dip721_approve' : ActorMethod<[Principal, bigint, [] | [bigint]], Result>,
+  'dip721_balance_of' : ActorMethod<[Principal], Result>,
+  'dip721_balance_of_account' : ActorMethod<[Account], Result>,
+  'dip721_burn' : ActorMethod<[bigint], Result>,
+  'dip721_cap_outbox' : ActorMethod<[], CapOutboxStats>,
+  'dip721_custodians' : ActorMethod<[], Array<Principal>>,
+  'dip721_cycles' : ActorMethod<[], bigint>,
+  'dip721_flush_cap_outbox' : ActorMethod<[], CapOutboxStats>,
+  'dip721_is_approved_for_all' : ActorMethod<[Principal, Principal], Result_1>,
+  'dip721_logo' : ActorMethod<[], [] | [string]>,
+  'dip721_max_supply' : ActorMethod<[], [] | [bigint]>,
+  'dip721_metadata' : ActorMethod<[], ManualReply>,
+  'dip721_mint' : ActorMethod<
+    [Principal, bigint, Array<[string, GenericValue]>],
+    Result,
+  >,
+  'dip721_mint_batch' : ActorMethod<[Array<MintRequest>], Result_5>,
+  'dip721_mint_next' : ActorMethod<
+    [Principal, Array<[string, GenericValue]>],
+    Result
+  >,
+  'dip721_name' : ActorMethod<[], [] | [string]>,
+  'dip721_operator_of' : ActorMethod<[bigint], Result_2>,
+  'dip721_operator_token_identifiers' : ActorMethod<[Principal], ManualReply_1>,
+  'dip721_operator_token_metadata' : ActorMethod<[Principal], ManualReply_2>,
+  'dip721_operators_of' : ActorMethod<[bigint], Result_3>,
+  'dip721_owner_account_of' : ActorMethod<[bigint], Result_6>,
+  'dip721_owner_of' : ActorMethod<[bigint], Result_2>,
+  'dip721_owner_token_identifiers' : ActorMethod<[Principal], ManualReply_1>,
+  'dip721_owner_token_identifiers_of_account' : ActorMethod<
+    [Account],
+    ManualReply_1
+  >,
+  'dip721_owner_token_metadata' : ActorMethod<[Principal], ManualReply_2>,
+  'dip721_resync_cap' : ActorMethod<[[] | [bigint], number], Result>,
+  'dip721_revoke' : ActorMethod<[Principal, bigint], Result>,
+  'dip721_set_approval_for_all' : ActorMethod<
+    [Principal, boolean, [] | [bigint]],
+    Result
+  >,
+  'dip721_set_cap' : ActorMethod<[[] | [Principal]], undefined>,
+  'dip721_set_custodians' : ActorMethod<[Array<Principal>], undefined>,
+  'dip721_set_logo' : ActorMethod<[string], undefined>,
+  'dip721_set_max_supply' : ActorMethod<[[] | [bigint]], undefined>,
+  'dip721_set_name' : ActorMethod<[string], undefined>,
+  'dip721_set_symbol' : ActorMethod<[string], undefined>,
+  'dip721_stats' : ActorMethod<[], Stats>,
+  'dip721_subscribe' : ActorMethod<[Principal, Array<EventType>], undefined>,
+  'dip721_subscribers' : ActorMethod<[], Array<[Principal, Subscriber]>>,
+  'dip721_supported_interfaces' : ActorMethod<[], Array<SupportedInterface>>,
+  'dip721_symbol' : ActorMethod<[], [] | [string]>,
+  'dip721_token_metadata' : ActorMethod<[bigint], ManualReply_3>,
+  'dip721_total_supply' : ActorMethod<[], bigint>,
+  'dip721_total_transactions' : ActorMethod<[], bigint>,
+  'dip721_total_unique_holders' : ActorMethod<[], bigint>,
+  'dip721_transaction' : ActorMethod<[bigint], Result_4>,
+  'dip721_transactions_by_caller' : ActorMethod<
+    [Principal, [] | [bigint], number],
+    TxEventPage,
+  >,
+  'dip721_transactions_by_counterparty' : ActorMethod<
+    [Principal, [] | [bigint], number],
+    TxEventPage,
+  >,
+  'dip721_transactions_by_token' : ActorMethod<
+    [bigint, [] | [bigint], number],
+    TxEventPage,
+  >,
+  'dip721_transfer' : ActorMethod<[Principal, bigint], Result>,
+  'dip721_transfer_and_notify' : ActorMethod<
+    [Principal, bigint, Array<number>],
+    Result
+  >,
+  'dip721_transfer_batch' : ActorMethod<[Array<TransferRequest>], Array<Result>>,
+  'dip721_transfer_from' : ActorMethod<[Principal, Principal, bigint], Result>,
+  'dip721_transfer_from_and_notify' : ActorMethod<
+    [Principal, Principal, bigint, Array<number>],
+    Result
+  >,
+  'dip721_transfer_from_batch' : ActorMethod<
+    [Array<TransferFromRequest>],
+    Array<Result>
+  >,
+  'dip721_transfer_to_account' : ActorMethod<
+    [[] | [Array<number>], Account, bigint],
+    Result
+  >,
+  'dip721_unsubscribe' : ActorMethod<[Principal], undefined>,
+  'extensions' : ActorMethod<[], Array<string>>,
+  'getMaxLimitDip721' : ActorMethod<[], number>,
+  'getMetadataDip721' : ActorMethod<[bigint], MetadataResult>,
+  'getMetadataForUserDip721' : ActorMethod<
+    [Principal],
+    Array<ExtendedMetadataResult>,
+  >,
+  'getRegistry' : ActorMethod<[], Array<[number, string]>>,
+  'getTokenIdsForUserDip721' : ActorMethod<[Principal], Array<bigint>>,
+  'git_commit_hash' : ActorMethod<[], string>,
+  'icrc3_get_blocks' : ActorMethod<[Array<GetBlocksArgs>], GetBlocksResult>,
+  'icrc3_get_tip_certificate' : ActorMethod<[], [] | [DataCertificate]>,
+  'icrc3_supported_block_types' : ActorMethod<[], Array<SupportedBlockType>>,
+  'icrc37_approve_collection' : ActorMethod<
+    [Array<ApproveCollectionArg>],
+    Array<[] | [Result_8]>
+  >,
+  'icrc37_approve_tokens' : ActorMethod<
+    [Array<ApproveTokenArg>],
+    Array<[] | [Result_9]>
+  >,
+  'icrc37_get_token_approvals' : ActorMethod<
+    [bigint, [] | [TokenApproval], [] | [bigint]],
+    Array<TokenApproval>
+  >,
+  'icrc37_is_approved' : ActorMethod<[Array<IsApprovedArg>], Array<boolean>>,
+  'icrc37_revoke_collection_approvals' : ActorMethod<
+    [Array<RevokeCollectionApprovalArg>],
+    Array<[] | [Result_10]>
+  >,
+  'icrc37_revoke_token_approvals' : ActorMethod<
+    [Array<RevokeTokenApprovalArg>],
+    Array<[] | [Result_11]>
+  >,
+  'icrc37_transfer_from' : ActorMethod<
+    [Array<TransferFromArg>],
+    Array<[] | [Result_12]>
+  >,
+  'icrc7_atomic_batch_transfers' : ActorMethod<[], [] | [boolean]>,
+  'icrc7_balance_of' : ActorMethod<[Array<Account>], Array<bigint>>,
+  'icrc7_collection_metadata' : ActorMethod<[], Array<[string, Value]>>,
+  'icrc7_default_take_value' : ActorMethod<[], [] | [bigint]>,
+  'icrc7_description' : ActorMethod<[], [] | [string]>,
+  'icrc7_logo' : ActorMethod<[], [] | [string]>,
+  'icrc7_max_memo_size' : ActorMethod<[], [] | [bigint]>,
+  'icrc7_max_query_batch_size' : ActorMethod<[], [] | [bigint]>,
+  'icrc7_max_take_value' : ActorMethod<[], [] | [bigint]>,
+  'icrc7_max_update_batch_size' : ActorMethod<[], [] | [bigint]>,
+  'icrc7_name' : ActorMethod<[], string>,
+  'icrc7_owner_of' : ActorMethod<[Array<bigint>], Array<[] | [Account]>>,
+  'icrc7_permitted_drift' : ActorMethod<[], [] | [bigint]>,
+  'icrc7_supply_cap' : ActorMethod<[], [] | [bigint]>,
+  'icrc7_supported_standards' : ActorMethod<[], Array<Standard>>,
+  'icrc7_symbol' : ActorMethod<[], string>,
+  'icrc7_token_metadata' : ActorMethod<
+    [Array<bigint>],
+    Array<[] | [Array<[string, Value]>]>
+  >,
+  'icrc7_tokens' : ActorMethod<[[] | [bigint], [] | [bigint]], Array<bigint>>,
+  'icrc7_tokens_of' : ActorMethod<
+    [Account, [] | [bigint], [] | [bigint]],
+    Array<bigint>
+  >,
+  'icrc7_total_supply' : ActorMethod<[], bigint>,
+  'icrc7_transfer' : ActorMethod<[Array<TransferArg>], Array<[] | [Result_7]>>,
+  'icrc7_tx_window' : ActorMethod<[], [] | [bigint]>,
+  'isApprovedForAll' : ActorMethod<[Principal, Principal], Result_1>,
+  'logo' : ActorMethod<[], [] | [string]>,
+  'logoDip721' : ActorMethod<[], LogoResult>,
+  'metadata' : ActorMethod<[], ManualReply>,
+  'mint' : ActorMethod<
+    [Principal, bigint, Array<[string, GenericValue]>],
+    Result,
+  >,
+  'mintDip721' : ActorMethod<[Principal, Array<MetadataPart>], MintReceipt>,
+  'name' : ActorMethod<[], [] | [string]>,
+  'nameDip721' : ActorMethod<[], string>,
+  'operatorOf' : ActorMethod<[bigint], Result_2>,
+  'operatorTokenIdentifiers' : ActorMethod<[Principal], ManualReply_1>,
+  'operatorTokenMetadata' : ActorMethod<[Principal], ManualReply_2>,
+  'ownerOf' : ActorMethod<[bigint], Result_2>,
+  'ownerOfDip721' : ActorMethod<[bigint], OwnerResult>,
+  'ownerTokenIdentifiers' : ActorMethod<[Principal], ManualReply_1>,
+  'ownerTokenMetadata' : ActorMethod<[Principal], ManualReply_2>,
+  'rust_toolchain_info' : ActorMethod<[], string>,
+  'safeTransferFromDip721' : ActorMethod<
+    [Principal, Principal, bigint],
+    TxReceipt,
+  >,
+  'setApprovalForAll' : ActorMethod<[Principal, boolean], Result>,
+  'setCustodians' : ActorMethod<[Array<Principal>], undefined>,
+  'setLogo' : ActorMethod<[string], undefined>,
+  'setName' : ActorMethod<[string], undefined>,
+  'setSymbol' : ActorMethod<[string], undefined>,
+  'stats' : ActorMethod<[], Stats>,
+  'supportedInterfaces' : ActorMethod<[], Array<SupportedInterface>>,
+  'supportedInterfacesDip721' : ActorMethod<[], Array<InterfaceId>>,
+  'symbol' : ActorMethod<[], [] | [string]>,
+  'symbolDip721' : ActorMethod<[], string>,
+  'tokenMetadata' : ActorMethod<[bigint], ManualReply_3>,
+  'tokens_ext' : ActorMethod<[string], ExtResult_1>,
+  'totalSupply' : ActorMethod<[], bigint>,
+  'totalSupplyDip721' : ActorMethod<[], bigint>,
+  'totalTransactions' : ActorMethod<[], bigint>,
+  'totalUniqueHolders' : ActorMethod<[], bigint>,
+  'transaction' : ActorMethod<[bigint], Result_4>,
+  'transfer' : ActorMethod<[ExtTransferRequest], ExtTransferResponse>,
+  'transferFrom' : ActorMethod<[Principal, Principal, bigint], Result>,
+  'transferFromDip721' : ActorMethod<[Principal, Principal, bigint], TxReceipt>,
+}
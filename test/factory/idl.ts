@@ -1,12 +1,22 @@
 // @ts-nocheck
 export const idlFactory = ({ IDL }) => {
   const Vec = IDL.Rec();
+  const Value = IDL.Rec();
+  const GetBlocksResult = IDL.Rec();
+  const HistoryBackend = IDL.Variant({
+    'Cap' : IDL.Null,
+    'Log' : IDL.Null,
+    'Both' : IDL.Null,
+    'Disabled' : IDL.Null,
+  });
   const InitArgs = IDL.Record({
     'cap' : IDL.Opt(IDL.Principal),
     'logo' : IDL.Opt(IDL.Text),
     'name' : IDL.Opt(IDL.Text),
     'custodians' : IDL.Opt(IDL.Vec(IDL.Principal)),
     'symbol' : IDL.Opt(IDL.Text),
+    'history' : IDL.Opt(HistoryBackend),
+    'max_supply' : IDL.Opt(IDL.Nat),
   });
   const NftError = IDL.Variant({
     'UnauthorizedOperator' : IDL.Null,
@@ -17,16 +27,30 @@ export const idlFactory = ({ IDL }) => {
     'OperatorNotFound' : IDL.Null,
     'ExistedNFT' : IDL.Null,
     'OwnerNotFound' : IDL.Null,
+    'TxNotFound' : IDL.Null,
+    'InvalidExpiry' : IDL.Null,
+    'MaxSupplyReached' : IDL.Null,
+    'InvalidSubaccount' : IDL.Null,
+    'Other' : IDL.Text,
+    'TransferRejected' : IDL.Null,
+    'TransferNotReturned' : IDL.Null,
   });
   const Result = IDL.Variant({ 'Ok' : IDL.Nat, 'Err' : NftError });
+  const Account = IDL.Record({
+    'owner' : IDL.Principal,
+    'subaccount' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+  });
   const Result_1 = IDL.Variant({ 'Ok' : IDL.Bool, 'Err' : NftError });
   const ManualReply = IDL.Record({
+    'cap' : IDL.Opt(IDL.Principal),
     'logo' : IDL.Opt(IDL.Text),
     'name' : IDL.Opt(IDL.Text),
     'created_at' : IDL.Nat64,
     'upgraded_at' : IDL.Nat64,
     'custodians' : IDL.Vec(IDL.Principal),
     'symbol' : IDL.Opt(IDL.Text),
+    'history' : HistoryBackend,
+    'max_supply' : IDL.Opt(IDL.Nat),
   });
   Vec.fill(
     IDL.Vec(
@@ -71,6 +95,17 @@ export const idlFactory = ({ IDL }) => {
     'Principal' : IDL.Principal,
     'TextContent' : IDL.Text,
   });
+  const MintRequest = IDL.Record({
+    'to' : IDL.Principal,
+    'token_identifier' : IDL.Nat,
+    'properties' : IDL.Vec(IDL.Tuple(IDL.Text, GenericValue)),
+  });
+  const TxIdRange = IDL.Record({ 'end' : IDL.Nat, 'start' : IDL.Nat });
+  const Result_5 = IDL.Variant({ 'Ok' : TxIdRange, 'Err' : NftError });
+  const Result_6 = IDL.Variant({
+    'Ok' : IDL.Opt(Account),
+    'Err' : NftError,
+  });
   const Result_2 = IDL.Variant({
     'Ok' : IDL.Opt(IDL.Principal),
     'Err' : NftError,
@@ -83,7 +118,9 @@ export const idlFactory = ({ IDL }) => {
     'transferred_at' : IDL.Opt(IDL.Nat64),
     'transferred_by' : IDL.Opt(IDL.Principal),
     'owner' : IDL.Opt(IDL.Principal),
+    'owner_subaccount' : IDL.Opt(IDL.Vec(IDL.Nat8)),
     'operator' : IDL.Opt(IDL.Principal),
+    'operators' : IDL.Vec(IDL.Principal),
     'approved_at' : IDL.Opt(IDL.Nat64),
     'approved_by' : IDL.Opt(IDL.Principal),
     'properties' : IDL.Vec(IDL.Tuple(IDL.Text, GenericValue)),
@@ -98,18 +135,250 @@ export const idlFactory = ({ IDL }) => {
     'Ok' : IDL.Vec(TokenMetadata),
     'Err' : NftError,
   });
+  const CapOutboxStats = IDL.Record({
+    'pending' : IDL.Nat64,
+    'failed' : IDL.Nat32,
+    'retry_at' : IDL.Opt(IDL.Nat64),
+    'last_error' : IDL.Opt(IDL.Text),
+    'resync_cursor' : IDL.Opt(IDL.Nat),
+  });
   const Stats = IDL.Record({
     'cycles' : IDL.Nat,
     'total_transactions' : IDL.Nat,
     'total_unique_holders' : IDL.Nat,
     'total_supply' : IDL.Nat,
   });
+  const EventType = IDL.Variant({
+    'Mint' : IDL.Null,
+    'Transfer' : IDL.Null,
+    'Burn' : IDL.Null,
+    'Approve' : IDL.Null,
+  });
+  const Subscriber = IDL.Record({
+    'event_types' : IDL.Vec(EventType),
+    'cursor' : IDL.Nat,
+    'failures' : IDL.Nat32,
+    'retry_at' : IDL.Nat64,
+  });
   const SupportedInterface = IDL.Variant({
     'Burn' : IDL.Null,
     'Mint' : IDL.Null,
     'Approval' : IDL.Null,
+    'TransactionHistory' : IDL.Null,
+  });
+  const Result_3 = IDL.Variant({
+    'Ok' : IDL.Vec(IDL.Principal),
+    'Err' : NftError,
   });
   const ManualReply_3 = IDL.Variant({ 'Ok' : TokenMetadata, 'Err' : NftError });
+  const TxEvent = IDL.Record({
+    'time' : IDL.Nat64,
+    'operation' : IDL.Text,
+    'details' : IDL.Vec(IDL.Tuple(IDL.Text, GenericValue)),
+    'caller' : IDL.Principal,
+  });
+  const Result_4 = IDL.Variant({ 'Ok' : TxEvent, 'Err' : NftError });
+  const TxEventPage = IDL.Record({
+    'events' : IDL.Vec(IDL.Tuple(IDL.Nat, TxEvent)),
+    'next_cursor' : IDL.Opt(IDL.Nat),
+  });
+  const TransferRequest = IDL.Record({
+    'to' : IDL.Principal,
+    'token_identifier' : IDL.Nat,
+  });
+  const TransferFromRequest = IDL.Record({
+    'to' : IDL.Principal,
+    'token_identifier' : IDL.Nat,
+    'owner' : IDL.Principal,
+  });
+  Value.fill(
+    IDL.Variant({
+      'Int' : IDL.Int,
+      'Map' : IDL.Vec(IDL.Tuple(IDL.Text, Value)),
+      'Nat' : IDL.Nat,
+      'Blob' : IDL.Vec(IDL.Nat8),
+      'Text' : IDL.Text,
+      'Array' : IDL.Vec(Value),
+    })
+  );
+  const Standard = IDL.Record({ 'url' : IDL.Text, 'name' : IDL.Text });
+  const TransferArg = IDL.Record({
+    'to' : Account,
+    'token_id' : IDL.Nat,
+    'memo' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'from_subaccount' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'created_at_time' : IDL.Opt(IDL.Nat64),
+  });
+  const TransferError = IDL.Variant({
+    'GenericError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+    'TooOld' : IDL.Null,
+    'InvalidRecipient' : IDL.Null,
+    'NonExistingTokenId' : IDL.Null,
+    'Unauthorized' : IDL.Null,
+    'CreatedInFuture' : IDL.Record({ 'ledger_time' : IDL.Nat64 }),
+    'Duplicate' : IDL.Record({ 'duplicate_of' : IDL.Nat }),
+    'GenericBatchError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+  });
+  const Result_7 = IDL.Variant({ 'Ok' : IDL.Nat, 'Err' : TransferError });
+  const GetBlocksArgs = IDL.Record({ 'start' : IDL.Nat, 'length' : IDL.Nat });
+  const BlockWithId = IDL.Record({ 'id' : IDL.Nat, 'block' : Value });
+  const ArchivedBlocks = IDL.Record({
+    'args' : IDL.Vec(GetBlocksArgs),
+    'callback' : IDL.Func(
+        [IDL.Vec(GetBlocksArgs)],
+        [GetBlocksResult],
+        ['query'],
+      ),
+  });
+  GetBlocksResult.fill(
+    IDL.Record({
+      'log_length' : IDL.Nat,
+      'blocks' : IDL.Vec(BlockWithId),
+      'archived_blocks' : IDL.Vec(ArchivedBlocks),
+    })
+  );
+  const DataCertificate = IDL.Record({
+    'certificate' : IDL.Vec(IDL.Nat8),
+    'hash_tree' : IDL.Vec(IDL.Nat8),
+  });
+  const SupportedBlockType = IDL.Record({
+    'url' : IDL.Text,
+    'block_type' : IDL.Text,
+  });
+  const ApprovalInfo = IDL.Record({
+    'memo' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'from_subaccount' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'created_at_time' : IDL.Nat64,
+    'expires_at' : IDL.Opt(IDL.Nat64),
+    'spender' : Account,
+  });
+  const ApproveCollectionArg = IDL.Record({ 'approval_info' : ApprovalInfo });
+  const ApproveCollectionError = IDL.Variant({
+    'GenericError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+    'TooOld' : IDL.Null,
+    'CreatedInFuture' : IDL.Record({ 'ledger_time' : IDL.Nat64 }),
+    'InvalidSpender' : IDL.Null,
+    'GenericBatchError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+  });
+  const Result_8 = IDL.Variant({
+    'Ok' : IDL.Nat,
+    'Err' : ApproveCollectionError,
+  });
+  const ApproveTokenArg = IDL.Record({
+    'token_id' : IDL.Nat,
+    'approval_info' : ApprovalInfo,
+  });
+  const ApproveTokenError = IDL.Variant({
+    'GenericError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+    'TooOld' : IDL.Null,
+    'NonExistingTokenId' : IDL.Null,
+    'Unauthorized' : IDL.Null,
+    'CreatedInFuture' : IDL.Record({ 'ledger_time' : IDL.Nat64 }),
+    'InvalidSpender' : IDL.Null,
+    'GenericBatchError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+  });
+  const Result_9 = IDL.Variant({ 'Ok' : IDL.Nat, 'Err' : ApproveTokenError });
+  const TokenApproval = IDL.Record({
+    'token_id' : IDL.Nat,
+    'approval_info' : ApprovalInfo,
+  });
+  const IsApprovedArg = IDL.Record({
+    'token_id' : IDL.Nat,
+    'from_subaccount' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'spender' : Account,
+  });
+  const RevokeCollectionApprovalArg = IDL.Record({
+    'memo' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'from_subaccount' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'created_at_time' : IDL.Opt(IDL.Nat64),
+    'spender' : IDL.Opt(Account),
+  });
+  const RevokeCollectionApprovalError = IDL.Variant({
+    'GenericError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+    'TooOld' : IDL.Null,
+    'CreatedInFuture' : IDL.Record({ 'ledger_time' : IDL.Nat64 }),
+    'ApprovalDoesNotExist' : IDL.Null,
+    'GenericBatchError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+  });
+  const Result_10 = IDL.Variant({
+    'Ok' : IDL.Nat,
+    'Err' : RevokeCollectionApprovalError,
+  });
+  const RevokeTokenApprovalArg = IDL.Record({
+    'token_id' : IDL.Nat,
+    'memo' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'from_subaccount' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'created_at_time' : IDL.Opt(IDL.Nat64),
+    'spender' : IDL.Opt(Account),
+  });
+  const RevokeTokenApprovalError = IDL.Variant({
+    'GenericError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+    'TooOld' : IDL.Null,
+    'NonExistingTokenId' : IDL.Null,
+    'Unauthorized' : IDL.Null,
+    'CreatedInFuture' : IDL.Record({ 'ledger_time' : IDL.Nat64 }),
+    'ApprovalDoesNotExist' : IDL.Null,
+    'GenericBatchError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+  });
+  const Result_11 = IDL.Variant({
+    'Ok' : IDL.Nat,
+    'Err' : RevokeTokenApprovalError,
+  });
+  const TransferFromArg = IDL.Record({
+    'to' : Account,
+    'spender_subaccount' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'token_id' : IDL.Nat,
+    'from' : Account,
+    'memo' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'created_at_time' : IDL.Opt(IDL.Nat64),
+  });
+  const TransferFromError = IDL.Variant({
+    'GenericError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+    'TooOld' : IDL.Null,
+    'InvalidRecipient' : IDL.Null,
+    'NonExistingTokenId' : IDL.Null,
+    'Unauthorized' : IDL.Null,
+    'CreatedInFuture' : IDL.Record({ 'ledger_time' : IDL.Nat64 }),
+    'Duplicate' : IDL.Record({ 'duplicate_of' : IDL.Nat }),
+    'GenericBatchError' : IDL.Record({
+      'message' : IDL.Text,
+      'error_code' : IDL.Nat,
+    }),
+  });
+  const Result_12 = IDL.Variant({ 'Ok' : IDL.Nat, 'Err' : TransferFromError });
   return IDL.Service({
     'approve' : IDL.Func([IDL.Principal, IDL.Nat], [Result], []),
     'balanceOf' : IDL.Func([IDL.Principal], [Result], ['query']),
@@ -117,23 +386,37 @@ export const idlFactory = ({ IDL }) => {
     'custodians' : IDL.Func([], [IDL.Vec(IDL.Principal)], ['query']),
     'cycles' : IDL.Func([], [IDL.Nat], ['query']),
     'dfx_info' : IDL.Func([], [IDL.Text], ['query']),
-    'dip721_approve' : IDL.Func([IDL.Principal, IDL.Nat], [Result], []),
+    'dip721_approve' : IDL.Func(
+        [IDL.Principal, IDL.Nat, IDL.Opt(IDL.Nat64)],
+        [Result],
+        [],
+      ),
     'dip721_balance_of' : IDL.Func([IDL.Principal], [Result], ['query']),
+    'dip721_balance_of_account' : IDL.Func([Account], [Result], ['query']),
     'dip721_burn' : IDL.Func([IDL.Nat], [Result], []),
+    'dip721_cap_outbox' : IDL.Func([], [CapOutboxStats], ['query']),
     'dip721_custodians' : IDL.Func([], [IDL.Vec(IDL.Principal)], ['query']),
     'dip721_cycles' : IDL.Func([], [IDL.Nat], ['query']),
+    'dip721_flush_cap_outbox' : IDL.Func([], [CapOutboxStats], []),
     'dip721_is_approved_for_all' : IDL.Func(
         [IDL.Principal, IDL.Principal],
         [Result_1],
         ['query'],
       ),
     'dip721_logo' : IDL.Func([], [IDL.Opt(IDL.Text)], ['query']),
+    'dip721_max_supply' : IDL.Func([], [IDL.Opt(IDL.Nat)], ['query']),
     'dip721_metadata' : IDL.Func([], [ManualReply], ['query']),
     'dip721_mint' : IDL.Func(
         [IDL.Principal, IDL.Nat, IDL.Vec(IDL.Tuple(IDL.Text, GenericValue))],
         [Result],
         [],
       ),
+    'dip721_mint_batch' : IDL.Func([IDL.Vec(MintRequest)], [Result_5], []),
+    'dip721_mint_next' : IDL.Func(
+        [IDL.Principal, IDL.Vec(IDL.Tuple(IDL.Text, GenericValue))],
+        [Result],
+        [],
+      ),
     'dip721_name' : IDL.Func([], [IDL.Opt(IDL.Text)], ['query']),
     'dip721_operator_of' : IDL.Func([IDL.Nat], [Result_2], ['query']),
     'dip721_operator_token_identifiers' : IDL.Func(
@@ -146,27 +429,52 @@ export const idlFactory = ({ IDL }) => {
         [ManualReply_2],
         ['query'],
       ),
+    'dip721_operators_of' : IDL.Func([IDL.Nat], [Result_3], ['query']),
+    'dip721_owner_account_of' : IDL.Func([IDL.Nat], [Result_6], ['query']),
     'dip721_owner_of' : IDL.Func([IDL.Nat], [Result_2], ['query']),
     'dip721_owner_token_identifiers' : IDL.Func(
         [IDL.Principal],
         [ManualReply_1],
         ['query'],
       ),
+    'dip721_owner_token_identifiers_of_account' : IDL.Func(
+        [Account],
+        [ManualReply_1],
+        ['query'],
+      ),
     'dip721_owner_token_metadata' : IDL.Func(
         [IDL.Principal],
         [ManualReply_2],
         ['query'],
       ),
+    'dip721_resync_cap' : IDL.Func(
+        [IDL.Opt(IDL.Nat), IDL.Nat32],
+        [Result],
+        [],
+      ),
+    'dip721_revoke' : IDL.Func([IDL.Principal, IDL.Nat], [Result], []),
     'dip721_set_approval_for_all' : IDL.Func(
-        [IDL.Principal, IDL.Bool],
+        [IDL.Principal, IDL.Bool, IDL.Opt(IDL.Nat64)],
         [Result],
         [],
       ),
+    'dip721_set_cap' : IDL.Func([IDL.Opt(IDL.Principal)], [], []),
     'dip721_set_custodians' : IDL.Func([IDL.Vec(IDL.Principal)], [], []),
     'dip721_set_logo' : IDL.Func([IDL.Text], [], []),
+    'dip721_set_max_supply' : IDL.Func([IDL.Opt(IDL.Nat)], [], []),
     'dip721_set_name' : IDL.Func([IDL.Text], [], []),
     'dip721_set_symbol' : IDL.Func([IDL.Text], [], []),
     'dip721_stats' : IDL.Func([], [Stats], ['query']),
+    'dip721_subscribe' : IDL.Func(
+        [IDL.Principal, IDL.Vec(EventType)],
+        [],
+        [],
+      ),
+    'dip721_subscribers' : IDL.Func(
+        [],
+        [IDL.Vec(IDL.Tuple(IDL.Principal, Subscriber))],
+        ['query'],
+      ),
     'dip721_supported_interfaces' : IDL.Func(
         [],
         [IDL.Vec(SupportedInterface)],
@@ -177,13 +485,171 @@ export const idlFactory = ({ IDL }) => {
     'dip721_total_supply' : IDL.Func([], [IDL.Nat], ['query']),
     'dip721_total_transactions' : IDL.Func([], [IDL.Nat], ['query']),
     'dip721_total_unique_holders' : IDL.Func([], [IDL.Nat], ['query']),
+    'dip721_transaction' : IDL.Func([IDL.Nat], [Result_4], ['query']),
+    'dip721_transactions_by_caller' : IDL.Func(
+        [IDL.Principal, IDL.Opt(IDL.Nat), IDL.Nat32],
+        [TxEventPage],
+        ['query'],
+      ),
+    'dip721_transactions_by_counterparty' : IDL.Func(
+        [IDL.Principal, IDL.Opt(IDL.Nat), IDL.Nat32],
+        [TxEventPage],
+        ['query'],
+      ),
+    'dip721_transactions_by_token' : IDL.Func(
+        [IDL.Nat, IDL.Opt(IDL.Nat), IDL.Nat32],
+        [TxEventPage],
+        ['query'],
+      ),
     'dip721_transfer' : IDL.Func([IDL.Principal, IDL.Nat], [Result], []),
+    'dip721_transfer_and_notify' : IDL.Func(
+        [IDL.Principal, IDL.Nat, IDL.Vec(IDL.Nat8)],
+        [Result],
+        [],
+      ),
+    'dip721_transfer_batch' : IDL.Func(
+        [IDL.Vec(TransferRequest)],
+        [IDL.Vec(Result)],
+        [],
+      ),
     'dip721_transfer_from' : IDL.Func(
         [IDL.Principal, IDL.Principal, IDL.Nat],
         [Result],
         [],
       ),
+    'dip721_transfer_from_and_notify' : IDL.Func(
+        [IDL.Principal, IDL.Principal, IDL.Nat, IDL.Vec(IDL.Nat8)],
+        [Result],
+        [],
+      ),
+    'dip721_transfer_from_batch' : IDL.Func(
+        [IDL.Vec(TransferFromRequest)],
+        [IDL.Vec(Result)],
+        [],
+      ),
+    'dip721_transfer_to_account' : IDL.Func(
+        [IDL.Opt(IDL.Vec(IDL.Nat8)), Account, IDL.Nat],
+        [Result],
+        [],
+      ),
+    'dip721_unsubscribe' : IDL.Func([IDL.Principal], [], []),
     'git_commit_hash' : IDL.Func([], [IDL.Text], ['query']),
+    'icrc3_get_blocks' : IDL.Func(
+        [IDL.Vec(GetBlocksArgs)],
+        [GetBlocksResult],
+        ['query'],
+      ),
+    'icrc3_get_tip_certificate' : IDL.Func(
+        [],
+        [IDL.Opt(DataCertificate)],
+        ['query'],
+      ),
+    'icrc3_supported_block_types' : IDL.Func(
+        [],
+        [IDL.Vec(SupportedBlockType)],
+        ['query'],
+      ),
+    'icrc37_approve_collection' : IDL.Func(
+        [IDL.Vec(ApproveCollectionArg)],
+        [IDL.Vec(IDL.Opt(Result_8))],
+        [],
+      ),
+    'icrc37_approve_tokens' : IDL.Func(
+        [IDL.Vec(ApproveTokenArg)],
+        [IDL.Vec(IDL.Opt(Result_9))],
+        [],
+      ),
+    'icrc37_get_token_approvals' : IDL.Func(
+        [IDL.Nat, IDL.Opt(TokenApproval), IDL.Opt(IDL.Nat)],
+        [IDL.Vec(TokenApproval)],
+        ['query'],
+      ),
+    'icrc37_is_approved' : IDL.Func(
+        [IDL.Vec(IsApprovedArg)],
+        [IDL.Vec(IDL.Bool)],
+        ['query'],
+      ),
+    'icrc37_revoke_collection_approvals' : IDL.Func(
+        [IDL.Vec(RevokeCollectionApprovalArg)],
+        [IDL.Vec(IDL.Opt(Result_10))],
+        [],
+      ),
+    'icrc37_revoke_token_approvals' : IDL.Func(
+        [IDL.Vec(RevokeTokenApprovalArg)],
+        [IDL.Vec(IDL.Opt(Result_11))],
+        [],
+      ),
+    'icrc37_transfer_from' : IDL.Func(
+        [IDL.Vec(TransferFromArg)],
+        [IDL.Vec(IDL.Opt(Result_12))],
+        [],
+      ),
+    'icrc7_atomic_batch_transfers' : IDL.Func(
+        [],
+        [IDL.Opt(IDL.Bool)],
+        ['query'],
+      ),
+    'icrc7_balance_of' : IDL.Func(
+        [IDL.Vec(Account)],
+        [IDL.Vec(IDL.Nat)],
+        ['query'],
+      ),
+    'icrc7_collection_metadata' : IDL.Func(
+        [],
+        [IDL.Vec(IDL.Tuple(IDL.Text, Value))],
+        ['query'],
+      ),
+    'icrc7_default_take_value' : IDL.Func([], [IDL.Opt(IDL.Nat)], ['query']),
+    'icrc7_description' : IDL.Func([], [IDL.Opt(IDL.Text)], ['query']),
+    'icrc7_logo' : IDL.Func([], [IDL.Opt(IDL.Text)], ['query']),
+    'icrc7_max_memo_size' : IDL.Func([], [IDL.Opt(IDL.Nat)], ['query']),
+    'icrc7_max_query_batch_size' : IDL.Func(
+        [],
+        [IDL.Opt(IDL.Nat)],
+        ['query'],
+      ),
+    'icrc7_max_take_value' : IDL.Func([], [IDL.Opt(IDL.Nat)], ['query']),
+    'icrc7_max_update_batch_size' : IDL.Func(
+        [],
+        [IDL.Opt(IDL.Nat)],
+        ['query'],
+      ),
+    'icrc7_name' : IDL.Func([], [IDL.Text], ['query']),
+    'icrc7_owner_of' : IDL.Func(
+        [IDL.Vec(IDL.Nat)],
+        [IDL.Vec(IDL.Opt(Account))],
+        ['query'],
+      ),
+    'icrc7_permitted_drift' : IDL.Func([], [IDL.Opt(IDL.Nat)], ['query']),
+    'icrc7_supply_cap' : IDL.Func([], [IDL.Opt(IDL.Nat)], ['query']),
+    'icrc7_supported_standards' : IDL.Func(
+        [],
+        [IDL.Vec(Standard)],
+        ['query'],
+      ),
+    'icrc7_symbol' : IDL.Func([], [IDL.Text], ['query']),
+    'icrc7_token_metadata' : IDL.Func(
+        [IDL.Vec(IDL.Nat)],
+        [IDL.Vec(IDL.Opt(IDL.Vec(IDL.Tuple(IDL.Text, Value))))],
+        ['query'],
+      ),
+    'icrc7_tokens' : IDL.Func(
+        [IDL.Opt(IDL.Nat), IDL.Opt(IDL.Nat)],
+        [IDL.Vec(IDL.Nat)],
+        ['query'],
+      ),
+    'icrc7_tokens_of' : IDL.Func(
+        [Account, IDL.Opt(IDL.Nat), IDL.Opt(IDL.Nat)],
+        [IDL.Vec(IDL.Nat)],
+        ['query'],
+      ),
+    'icrc7_total_supply' : IDL.Func([], [IDL.Nat], ['query']),
+    'icrc7_transfer' : IDL.Func(
+        [IDL.Vec(TransferArg)],
+        [IDL.Vec(IDL.Opt(Result_7))],
+        [],
+      ),
+    'icrc7_tx_window' : IDL.Func([], [IDL.Opt(IDL.Nat)], ['query']),
     'isApprovedForAll' : IDL.Func(
         [IDL.Principal, IDL.Principal],
         [Result_1],
@@ -236,6 +702,7 @@ export const idlFactory = ({ IDL }) => {
     'totalSupply' : IDL.Func([], [IDL.Nat], ['query']),
     'totalTransactions' : IDL.Func([], [IDL.Nat], ['query']),
     'totalUniqueHolders' : IDL.Func([], [IDL.Nat], ['query']),
+    'transaction' : IDL.Func([IDL.Nat], [Result_4], ['query']),
     'transfer' : IDL.Func([IDL.Principal, IDL.Nat], [Result], []),
     'transferFrom' : IDL.Func(
         [IDL.Principal, IDL.Principal, IDL.Nat],
@@ -245,12 +712,20 @@ export const idlFactory = ({ IDL }) => {
   });
 };
 export const init = ({ IDL }) => {
+  const HistoryBackend = IDL.Variant({
+    'Cap' : IDL.Null,
+    'Log' : IDL.Null,
+    'Both' : IDL.Null,
+    'Disabled' : IDL.Null,
+  });
   const InitArgs = IDL.Record({
     'cap' : IDL.Opt(IDL.Principal),
     'logo' : IDL.Opt(IDL.Text),
     'name' : IDL.Opt(IDL.Text),
     'custodians' : IDL.Opt(IDL.Vec(IDL.Principal)),
     'symbol' : IDL.Opt(IDL.Text),
+    'history' : IDL.Opt(HistoryBackend),
+    'max_supply' : IDL.Opt(IDL.Nat),
   });
   return [IDL.Opt(InitArgs)];
 };
@@ -0,0 +1,103 @@
+import {createHash} from "crypto";
+
+import {Principal} from "@dfinity/principal";
+import test from "ava";
+
+import {ExtTransferRequest} from "../factory/compat/idl.d";
+import {
+  aliceCompatActor,
+  aliceIdentity,
+  bobCompatActor,
+  bobIdentity,
+  compatCanisterId,
+  custodianCompatActor
+} from "../setup";
+
+const crc32 = (bytes: Uint8Array): number => {
+  let crc = 0xffffffff;
+  for (const byte of bytes) {
+    crc ^= byte;
+    for (let bit = 0; bit < 8; bit++) {
+      crc = crc & 1 ? (crc >>> 1) ^ 0xedb88320 : crc >>> 1;
+    }
+  }
+  return (crc ^ 0xffffffff) >>> 0;
+};
+
+const accountIdentifier = (owner: Principal): string => {
+  const hash = createHash("sha224")
+    .update(Buffer.from("\x0Aaccount-id"))
+    .update(owner.toUint8Array())
+    .update(Buffer.alloc(32))
+    .digest();
+  const checksum = Buffer.alloc(4);
+  checksum.writeUInt32BE(crc32(hash));
+  return Buffer.concat([checksum, hash]).toString("hex");
+};
+
+const tokenIdentifier = (index: number): string => {
+  const bytes = Buffer.alloc(4);
+  bytes.writeUInt32BE(index);
+  return Principal.fromUint8Array(
+    Buffer.concat([Buffer.from("\x0Atid"), Principal.fromText(compatCanisterId).toUint8Array(), bytes])
+  ).toText();
+};
+
+const transferRequest = (from: Principal, to: ExtTransferRequest["to"]): ExtTransferRequest => ({
+  from: {principal: from},
+  to,
+  token: tokenIdentifier(1),
+  amount: BigInt(1),
+  memo: [],
+  notify: false,
+  subaccount: []
+});
+
+const aliceAddress = accountIdentifier(aliceIdentity.getPrincipal());
+const bobAddress = accountIdentifier(bobIdentity.getPrincipal());
+
+test.serial("mint NFT and verify EXT information.", async t => {
+  t.deepEqual(await custodianCompatActor.dip721_mint(aliceIdentity.getPrincipal(), BigInt(1), []), {Ok: BigInt(0)});
+
+  t.deepEqual(await aliceCompatActor.bearer(tokenIdentifier(1)), {ok: aliceAddress});
+  t.deepEqual(await aliceCompatActor.tokens_ext(aliceAddress), {ok: [[1, [], []]]});
+  t.deepEqual(await aliceCompatActor.getRegistry(), [[1, aliceAddress]]);
+  t.deepEqual(await aliceCompatActor.bearer(tokenIdentifier(2)), {err: {InvalidToken: tokenIdentifier(2)}});
+});
+
+test.serial("verify EXT transfers to unknown or invalid account identifiers are rejected.", async t => {
+  // bob never received an nft, his account identifier can't be resolved
+  t.deepEqual(await aliceCompatActor.transfer(transferRequest(aliceIdentity.getPrincipal(), {address: bobAddress})), {
+    err: {Other: "unknown account identifier, transfer to the principal instead"}
+  });
+  t.deepEqual(await aliceCompatActor.transfer(transferRequest(aliceIdentity.getPrincipal(), {address: "zz"})), {
+    err: {Other: "invalid account identifier"}
+  });
+  const badChecksum = (bobAddress.startsWith("00") ? "01" : "00") + bobAddress.slice(2);
+  t.deepEqual(
+    await aliceCompatActor.transfer(transferRequest(aliceIdentity.getPrincipal(), {address: badChecksum})),
+    {err: {Other: "invalid account identifier checksum"}}
+  );
+  t.deepEqual(await aliceCompatActor.tokens_ext(bobAddress), {
+    err: {Other: "unknown account identifier, transfer to the principal instead"}
+  });
+  t.deepEqual(await aliceCompatActor.bearer(tokenIdentifier(1)), {ok: aliceAddress});
+});
+
+test.serial("verify EXT transfers by principal and by account identifier.", async t => {
+  t.deepEqual(
+    await aliceCompatActor.transfer(
+      transferRequest(aliceIdentity.getPrincipal(), {principal: bobIdentity.getPrincipal()})
+    ),
+    {ok: BigInt(1)}
+  );
+  t.deepEqual(await bobCompatActor.bearer(tokenIdentifier(1)), {ok: bobAddress});
+  t.deepEqual(await bobCompatActor.tokens_ext(aliceAddress), {err: {Other: "No tokens"}});
+
+  // alice doesn't hold an nft anymore, her account identifier still resolves
+  t.deepEqual(await bobCompatActor.transfer(transferRequest(bobIdentity.getPrincipal(), {address: aliceAddress})), {
+    ok: BigInt(1)
+  });
+  t.deepEqual(await bobCompatActor.bearer(tokenIdentifier(1)), {ok: aliceAddress});
+  t.deepEqual(await bobCompatActor.getRegistry(), [[1, aliceAddress]]);
+});
@@ -0,0 +1,32 @@
+import {Principal} from "@dfinity/principal";
+import test from "ava";
+
+import {aliceCompatActor, compatCanisterId, custodianCompatActor, custodianUnloggedActor} from "../setup";
+
+test.serial("verify ICRC-3 is only advertised with the tx log.", async t => {
+  t.true((await aliceCompatActor.icrc7_supported_standards()).some(({name}) => name === "ICRC-3"));
+  t.false((await custodianUnloggedActor.icrc7_supported_standards()).some(({name}) => name === "ICRC-3"));
+  const interfaces = await custodianUnloggedActor.dip721_supported_interfaces();
+  t.false(interfaces.some(supported => "TransactionHistory" in supported));
+  t.deepEqual((await custodianUnloggedActor.dip721_metadata()).history, {Disabled: null});
+});
+
+test.serial("verify the ICRC-3 endpoints report the disabled tx log.", async t => {
+  const message = /the ICRC-3 log is disabled by the history backend/;
+  await t.throwsAsync(custodianUnloggedActor.icrc3_get_blocks([{start: BigInt(0), length: BigInt(10)}]), {message});
+  await t.throwsAsync(custodianUnloggedActor.icrc3_get_tip_certificate(), {message});
+  await t.throwsAsync(custodianUnloggedActor.icrc3_supported_block_types(), {message});
+});
+
+test.serial("verify subscriptions need the tx log.", async t => {
+  const subscriber = Principal.fromText(compatCanisterId);
+  await t.throwsAsync(custodianUnloggedActor.dip721_subscribe(subscriber, [{Transfer: null}]), {
+    message: /events are read from the tx log, which the history backend disables/
+  });
+  t.deepEqual(await custodianUnloggedActor.dip721_subscribers(), []);
+
+  // logged canisters accept them
+  await custodianCompatActor.dip721_subscribe(subscriber, [{Transfer: null}]);
+  t.is((await custodianCompatActor.dip721_subscribers()).length, 1);
+  await custodianCompatActor.dip721_unsubscribe(subscriber);
+});
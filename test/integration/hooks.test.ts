@@ -0,0 +1,59 @@
+import {Principal} from "@dfinity/principal";
+import test from "ava";
+
+import {
+  aliceHookedActor,
+  aliceIdentity,
+  bobHookedActor,
+  bobIdentity,
+  custodianHookedActor,
+  hookedCanisterId,
+  hookLogActor
+} from "../setup";
+
+const alice = aliceIdentity.getPrincipal();
+const bob = bobIdentity.getPrincipal();
+
+test.serial("verify vetoed mints change nothing.", async t => {
+  t.deepEqual(await custodianHookedActor.dip721_mint(alice, BigInt(1), [["vetoed", {BoolContent: true}]]), {
+    Err: {Other: "mint vetoed"}
+  });
+  t.deepEqual(await aliceHookedActor.dip721_token_metadata(BigInt(1)), {Err: {TokenNotFound: null}});
+  t.deepEqual(await aliceHookedActor.dip721_total_transactions(), BigInt(0));
+  t.deepEqual(await hookLogActor.hook_log(), []);
+});
+
+test.serial("verify the after hooks run for every applied operation.", async t => {
+  t.deepEqual(await custodianHookedActor.dip721_mint(alice, BigInt(1), []), {Ok: BigInt(0)});
+  t.deepEqual(await custodianHookedActor.dip721_mint(alice, BigInt(2), [["locked", {BoolContent: true}]]), {
+    Ok: BigInt(1)
+  });
+  t.deepEqual(await hookLogActor.hook_log(), [`mint 1 to ${alice.toText()}`, `mint 2 to ${alice.toText()}`]);
+
+  t.deepEqual(await aliceHookedActor.dip721_transfer(bob, BigInt(2)), {Err: {Other: "transfer vetoed"}});
+  t.deepEqual(await aliceHookedActor.dip721_owner_of(BigInt(2)), {Ok: [alice]});
+
+  t.deepEqual(await aliceHookedActor.dip721_transfer(bob, BigInt(1)), {Ok: BigInt(2)});
+  t.deepEqual(await bobHookedActor.dip721_burn(BigInt(1)), {Ok: BigInt(3)});
+  t.deepEqual(await hookLogActor.hook_log(), [
+    `mint 1 to ${alice.toText()}`,
+    `mint 2 to ${alice.toText()}`,
+    `transfer 1 from ${alice.toText()} to ${bob.toText()}`,
+    `burn 1 of ${bob.toText()}`
+  ]);
+});
+
+test.serial("verify notified transfers whose return is vetoed are reported as not returned.", async t => {
+  const receiver = Principal.fromText(hookedCanisterId);
+
+  // the example canister doesn't accept nfts and vetoes transfers out of itself
+  t.deepEqual(await custodianHookedActor.dip721_mint(alice, BigInt(3), []), {Ok: BigInt(4)});
+  t.deepEqual(await aliceHookedActor.dip721_transfer_and_notify(receiver, BigInt(3), []), {
+    Err: {TransferNotReturned: null}
+  });
+  t.deepEqual(await aliceHookedActor.dip721_owner_of(BigInt(3)), {Ok: [receiver]});
+  t.deepEqual((await hookLogActor.hook_log()).slice(-2), [
+    `mint 3 to ${alice.toText()}`,
+    `transfer 3 from ${alice.toText()} to ${receiver.toText()}`
+  ]);
+});
@@ -1,3 +1,6 @@
+import {execSync} from "child_process";
+
+import {Actor} from "@dfinity/agent";
 import {Principal} from "@dfinity/principal10";
 import {CapRoot} from "@psychedelic/cap-js";
 import test from "ava";
@@ -12,7 +15,11 @@ import {
   custodianIdentity,
   johnActor,
   johnIdentity,
+  capCanisterId,
   capRouter,
+  capStubCanisterId,
+  nextCapCanisterId,
+  nextCapRouter,
   nftCanisterId,
   // stringify,
   host
@@ -209,6 +216,10 @@ test.serial("error on query non-existed information.", async t => {
 });
 
 test.serial("Get cap token contract root bucket", async t => {
+  // the first flush of the global timer creates the root bucket
+  for (let i = 0; i < 10 && (await custodianActor.dip721_cap_outbox()).pending > BigInt(0); i++) {
+    await new Promise(resolve => setTimeout(resolve, 1000));
+  }
   const principal = Principal.fromText(nftCanisterId);
   const {canister: bucketResponse} = await capRouter.get_token_contract_root_bucket({
     tokenId: principal,
@@ -238,6 +249,23 @@ test.serial("verify basic mint transaction.", async t => {
   });
 });
 
+test.serial("verify basic mint transaction from canister history.", async t => {
+  (await Promise.all(allActors.map(actor => actor.dip721_transaction(BigInt(0))))).forEach(result => {
+    t.like(result, {
+      Ok: {
+        caller: custodianIdentity.getPrincipal(),
+        operation: "mint",
+        details: [["to", {Principal: aliceIdentity.getPrincipal()}], ["token_identifier", {NatContent: BigInt(1)}]]
+      }
+    });
+  });
+
+  // transaction error when non-exist tx
+  (await Promise.all(allActors.map(actor => actor.dip721_transaction(BigInt(1))))).forEach(result => {
+    t.deepEqual(result, {Err: {TxNotFound: null}});
+  });
+});
+
 test.serial("mint NFTs.", async t => {
   t.deepEqual(
     await custodianActor.dip721_mint(aliceIdentity.getPrincipal(), BigInt(2), [["B", {Int64Content: BigInt(1234)}]]),
@@ -296,6 +324,51 @@ test.serial("verify transactions after mints", async t => {
   });
 });
 
+test.serial("verify paginated transaction history after mints.", async t => {
+  // by token
+  (await Promise.all(allActors.map(actor => actor.dip721_transactions_by_token(BigInt(2), [], 10)))).forEach(result => {
+    t.is(result.events.length, 1);
+    t.is(result.events[0][0], BigInt(1));
+    t.like(result.events[0][1], {caller: custodianIdentity.getPrincipal(), operation: "mint"});
+    t.deepEqual(result.next_cursor, []);
+  });
+
+  // by caller, first page
+  (
+    await Promise.all(
+      allActors.map(actor => actor.dip721_transactions_by_caller(custodianIdentity.getPrincipal(), [], 3))
+    )
+  ).forEach(result => {
+    t.deepEqual(result.events.map(([txId]) => txId), [BigInt(0), BigInt(1), BigInt(2)]);
+    t.deepEqual(result.next_cursor, [BigInt(3)]);
+  });
+
+  // by caller, next page
+  (
+    await Promise.all(
+      allActors.map(actor => actor.dip721_transactions_by_caller(custodianIdentity.getPrincipal(), [BigInt(3)], 3))
+    )
+  ).forEach(result => {
+    t.deepEqual(result.events.map(([txId]) => txId), [BigInt(3)]);
+    t.deepEqual(result.next_cursor, []);
+  });
+
+  // by counterparty
+  (
+    await Promise.all(
+      allActors.map(actor => actor.dip721_transactions_by_counterparty(aliceIdentity.getPrincipal(), [], 10))
+    )
+  ).forEach(result => {
+    t.deepEqual(result.events.map(([txId]) => txId), [BigInt(0), BigInt(1)]);
+    t.deepEqual(result.next_cursor, []);
+  });
+
+  // empty page when nothing matches
+  (await Promise.all(allActors.map(actor => actor.dip721_transactions_by_token(BigInt(5), [], 10)))).forEach(result => {
+    t.deepEqual(result, {events: [], next_cursor: []});
+  });
+});
+
 test.serial("verify stats after mint.", async t => {
   // verify total_transactions
   (await Promise.all(allActors.map(actor => actor.dip721_total_transactions()))).forEach(result => {
@@ -521,12 +594,12 @@ test.serial("verify mint information.", async t => {
 });
 
 test.serial("approve NFTs.", async t => {
-  t.deepEqual(await bobActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(3)), {Ok: BigInt(4)});
-  t.deepEqual(await johnActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(4)), {Ok: BigInt(5)});
-  t.deepEqual(await aliceActor.dip721_approve(bobIdentity.getPrincipal(), BigInt(1)), {Ok: BigInt(6)});
-  t.deepEqual(await aliceActor.dip721_approve(johnIdentity.getPrincipal(), BigInt(2)), {Ok: BigInt(7)});
+  t.deepEqual(await bobActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(3), []), {Ok: BigInt(4)});
+  t.deepEqual(await johnActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(4), []), {Ok: BigInt(5)});
+  t.deepEqual(await aliceActor.dip721_approve(bobIdentity.getPrincipal(), BigInt(1), []), {Ok: BigInt(6)});
+  t.deepEqual(await aliceActor.dip721_approve(johnIdentity.getPrincipal(), BigInt(2), []), {Ok: BigInt(7)});
 
-  // verify is_approved_for_all
+  // verify is_approved_for_all (per nft approvals don't approve for all)
   (
     await Promise.all([
       ...allActors.map(actor =>
@@ -534,11 +607,7 @@ test.serial("approve NFTs.", async t => {
       ),
       ...allActors.map(actor =>
         actor.dip721_is_approved_for_all(johnIdentity.getPrincipal(), aliceIdentity.getPrincipal())
-      )
-    ])
-  ).forEach(result => t.deepEqual(result, {Ok: true}));
-  (
-    await Promise.all([
+      ),
       ...allActors.map(actor =>
         actor.dip721_is_approved_for_all(aliceIdentity.getPrincipal(), bobIdentity.getPrincipal())
       ),
@@ -918,10 +987,10 @@ test.serial("verify approve information.", async t => {
 });
 
 test.serial("error on self approve or approve non-existed operator.", async t => {
-  t.deepEqual(await aliceActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(1)), {Err: {SelfApprove: null}});
-  t.deepEqual(await aliceActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(2)), {Err: {SelfApprove: null}});
-  t.deepEqual(await bobActor.dip721_approve(bobIdentity.getPrincipal(), BigInt(3)), {Err: {SelfApprove: null}});
-  t.deepEqual(await johnActor.dip721_approve(johnIdentity.getPrincipal(), BigInt(4)), {Err: {SelfApprove: null}});
+  t.deepEqual(await aliceActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(1), []), {Err: {SelfApprove: null}});
+  t.deepEqual(await aliceActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(2), []), {Err: {SelfApprove: null}});
+  t.deepEqual(await bobActor.dip721_approve(bobIdentity.getPrincipal(), BigInt(3), []), {Err: {SelfApprove: null}});
+  t.deepEqual(await johnActor.dip721_approve(johnIdentity.getPrincipal(), BigInt(4), []), {Err: {SelfApprove: null}});
 
   // operator_token_metadata error when non-existed operator
   (
@@ -939,25 +1008,25 @@ test.serial("error on self approve or approve non-existed operator.", async t =>
 });
 
 test.serial("error on unauthorize owner when approve.", async t => {
-  t.deepEqual(await custodianActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(1)), {
+  t.deepEqual(await custodianActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(1), []), {
     Err: {UnauthorizedOwner: null}
   });
-  t.deepEqual(await custodianActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(2)), {
+  t.deepEqual(await custodianActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(2), []), {
     Err: {UnauthorizedOwner: null}
   });
-  t.deepEqual(await custodianActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(3)), {
+  t.deepEqual(await custodianActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(3), []), {
     Err: {UnauthorizedOwner: null}
   });
-  t.deepEqual(await custodianActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(4)), {
+  t.deepEqual(await custodianActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(4), []), {
     Err: {UnauthorizedOwner: null}
   });
 });
 
 test.serial("approve NFTs (new operator).", async t => {
-  t.deepEqual(await aliceActor.dip721_approve(custodianIdentity.getPrincipal(), BigInt(1)), {Ok: BigInt(8)});
-  t.deepEqual(await aliceActor.dip721_approve(custodianIdentity.getPrincipal(), BigInt(2)), {Ok: BigInt(9)});
-  t.deepEqual(await bobActor.dip721_approve(custodianIdentity.getPrincipal(), BigInt(3)), {Ok: BigInt(10)});
-  t.deepEqual(await johnActor.dip721_approve(custodianIdentity.getPrincipal(), BigInt(4)), {Ok: BigInt(11)});
+  t.deepEqual(await aliceActor.dip721_approve(custodianIdentity.getPrincipal(), BigInt(1), []), {Ok: BigInt(8)});
+  t.deepEqual(await aliceActor.dip721_approve(custodianIdentity.getPrincipal(), BigInt(2), []), {Ok: BigInt(9)});
+  t.deepEqual(await bobActor.dip721_approve(custodianIdentity.getPrincipal(), BigInt(3), []), {Ok: BigInt(10)});
+  t.deepEqual(await johnActor.dip721_approve(custodianIdentity.getPrincipal(), BigInt(4), []), {Ok: BigInt(11)});
 });
 
 test.serial("verify stats after approve (new operator).", async t => {
@@ -1236,39 +1305,37 @@ test.serial("verify approve information after updated to new operator.", async t
   });
 });
 
-test.serial("error on querying old operator information.", async t => {
-  // operator_token_metadata error when non-existed operator
-  (
-    await Promise.all(allActors.map(actor => actor.dip721_operator_token_metadata(aliceIdentity.getPrincipal())))
-  ).forEach(result => {
-    t.deepEqual(result, {Err: {OperatorNotFound: null}});
+test.serial("verify previous operators are kept after approving a new operator.", async t => {
+  // verify operators_of
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(1))))).forEach(result => {
+    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal(), custodianIdentity.getPrincipal()]});
   });
-  (await Promise.all(allActors.map(actor => actor.dip721_operator_token_metadata(bobIdentity.getPrincipal())))).forEach(
-    result => {
-      t.deepEqual(result, {Err: {OperatorNotFound: null}});
-    }
-  );
-  (
-    await Promise.all(allActors.map(actor => actor.dip721_operator_token_metadata(johnIdentity.getPrincipal())))
-  ).forEach(result => {
-    t.deepEqual(result, {Err: {OperatorNotFound: null}});
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(2))))).forEach(result => {
+    t.deepEqual(result, {Ok: [johnIdentity.getPrincipal(), custodianIdentity.getPrincipal()]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(3))))).forEach(result => {
+    t.deepEqual(result, {Ok: [aliceIdentity.getPrincipal(), custodianIdentity.getPrincipal()]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(4))))).forEach(result => {
+    t.deepEqual(result, {Ok: [aliceIdentity.getPrincipal(), custodianIdentity.getPrincipal()]});
   });
 
-  // operator_token_identifiers error when non-existed operator
+  // verify operator_token_identifiers
   (
     await Promise.all(allActors.map(actor => actor.dip721_operator_token_identifiers(aliceIdentity.getPrincipal())))
   ).forEach(result => {
-    t.deepEqual(result, {Err: {OperatorNotFound: null}});
+    t.true((result as {Ok: Array<bigint>}).Ok.includes(BigInt(3)));
+    t.true((result as {Ok: Array<bigint>}).Ok.includes(BigInt(4)));
   });
   (
     await Promise.all(allActors.map(actor => actor.dip721_operator_token_identifiers(bobIdentity.getPrincipal())))
   ).forEach(result => {
-    t.deepEqual(result, {Err: {OperatorNotFound: null}});
+    t.deepEqual(result, {Ok: [BigInt(1)]});
   });
   (
     await Promise.all(allActors.map(actor => actor.dip721_operator_token_identifiers(johnIdentity.getPrincipal())))
   ).forEach(result => {
-    t.deepEqual(result, {Err: {OperatorNotFound: null}});
+    t.deepEqual(result, {Ok: [BigInt(2)]});
   });
 });
 
@@ -1335,7 +1402,7 @@ test.serial("error on unauthorized owner when calling transfer_from.", async t =
 // invalid operator
 test.serial("error on unauthorized operator when calling transfer_from.", async t => {
   t.deepEqual(
-    await bobActor.dip721_transfer_from(aliceIdentity.getPrincipal(), custodianIdentity.getPrincipal(), BigInt(1)),
+    await johnActor.dip721_transfer_from(aliceIdentity.getPrincipal(), custodianIdentity.getPrincipal(), BigInt(1)),
     {
       Err: {UnauthorizedOperator: null}
     }
@@ -1353,7 +1420,7 @@ test.serial("error on unauthorized operator when calling transfer_from.", async
     }
   );
   t.deepEqual(
-    await aliceActor.dip721_transfer_from(johnIdentity.getPrincipal(), custodianIdentity.getPrincipal(), BigInt(4)),
+    await bobActor.dip721_transfer_from(johnIdentity.getPrincipal(), custodianIdentity.getPrincipal(), BigInt(4)),
     {
       Err: {UnauthorizedOperator: null}
     }
@@ -1995,8 +2062,8 @@ test.serial("verify transfer information.", async t => {
 });
 
 test.serial("set_approval_for_all(true).", async t => {
-  t.deepEqual(await bobActor.dip721_set_approval_for_all(johnIdentity.getPrincipal(), true), {Ok: BigInt(20)});
-  t.deepEqual(await johnActor.dip721_set_approval_for_all(bobIdentity.getPrincipal(), true), {Ok: BigInt(21)});
+  t.deepEqual(await bobActor.dip721_set_approval_for_all(johnIdentity.getPrincipal(), true, []), {Ok: BigInt(20)});
+  t.deepEqual(await johnActor.dip721_set_approval_for_all(bobIdentity.getPrincipal(), true, []), {Ok: BigInt(21)});
 
   // verify is_approved_for_all
   (
@@ -2095,29 +2162,25 @@ test.serial("verify set_approval_for_all(true) information.", async t => {
     t.deepEqual(result, {Ok: [bobIdentity.getPrincipal()]});
   });
 
-  // verify operator_of
-  (await Promise.all(allActors.map(actor => actor.dip721_operator_of(BigInt(1))))).forEach(result => {
-    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal()]});
-  });
-  (await Promise.all(allActors.map(actor => actor.dip721_operator_of(BigInt(2))))).forEach(result => {
-    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal()]});
-  });
-  (await Promise.all(allActors.map(actor => actor.dip721_operator_of(BigInt(3))))).forEach(result => {
-    t.deepEqual(result, {Ok: [johnIdentity.getPrincipal()]});
-  });
-  (await Promise.all(allActors.map(actor => actor.dip721_operator_of(BigInt(4))))).forEach(result => {
-    t.deepEqual(result, {Ok: [johnIdentity.getPrincipal()]});
-  });
+  // verify operator_of (approval for all doesn't touch per nft operators)
+  for (const tokenIdentifier of [BigInt(1), BigInt(2), BigInt(3), BigInt(4)]) {
+    (await Promise.all(allActors.map(actor => actor.dip721_operator_of(tokenIdentifier)))).forEach(result => {
+      t.deepEqual(result, {Ok: []});
+    });
+    (await Promise.all(allActors.map(actor => actor.dip721_operators_of(tokenIdentifier)))).forEach(result => {
+      t.deepEqual(result, {Ok: []});
+    });
+  }
 
   // verify token
   (await Promise.all(allActors.map(actor => actor.dip721_token_metadata(BigInt(1))))).forEach(result => {
     t.like(result, {
       Ok: {
         owner: [johnIdentity.getPrincipal()],
-        operator: [bobIdentity.getPrincipal()],
+        operator: [],
         properties: [["A", {Nat64Content: BigInt(9999)}]],
         is_burned: false,
-        approved_by: [johnIdentity.getPrincipal()],
+        approved_by: [aliceIdentity.getPrincipal()],
         burned_by: [],
         burned_at: [],
         token_identifier: BigInt(1),
@@ -2130,10 +2193,10 @@ test.serial("verify set_approval_for_all(true) information.", async t => {
     t.like(result, {
       Ok: {
         owner: [johnIdentity.getPrincipal()],
-        operator: [bobIdentity.getPrincipal()],
+        operator: [],
         properties: [["B", {Int64Content: BigInt(1234)}]],
         is_burned: false,
-        approved_by: [johnIdentity.getPrincipal()],
+        approved_by: [aliceIdentity.getPrincipal()],
         burned_by: [],
         burned_at: [],
         token_identifier: BigInt(2),
@@ -2146,7 +2209,7 @@ test.serial("verify set_approval_for_all(true) information.", async t => {
     t.like(result, {
       Ok: {
         owner: [bobIdentity.getPrincipal()],
-        operator: [johnIdentity.getPrincipal()],
+        operator: [],
         properties: [["C", {Int32Content: 5678}]],
         is_burned: false,
         approved_by: [bobIdentity.getPrincipal()],
@@ -2162,10 +2225,10 @@ test.serial("verify set_approval_for_all(true) information.", async t => {
     t.like(result, {
       Ok: {
         owner: [bobIdentity.getPrincipal()],
-        operator: [johnIdentity.getPrincipal()],
+        operator: [],
         properties: [["D", {TextContent: "∆≈ç√∫"}]],
         is_burned: false,
-        approved_by: [bobIdentity.getPrincipal()],
+        approved_by: [johnIdentity.getPrincipal()],
         burned_by: [],
         burned_at: [],
         token_identifier: BigInt(4),
@@ -2184,10 +2247,10 @@ test.serial("verify set_approval_for_all(true) information.", async t => {
         (result as {Ok: Array<TokenMetadata>}).Ok.find(token_metadata => token_metadata.token_identifier === BigInt(1)),
         {
           owner: [johnIdentity.getPrincipal()],
-          operator: [bobIdentity.getPrincipal()],
+          operator: [],
           properties: [["A", {Nat64Content: BigInt(9999)}]],
           is_burned: false,
-          approved_by: [johnIdentity.getPrincipal()],
+          approved_by: [aliceIdentity.getPrincipal()],
           burned_by: [],
           burned_at: [],
           token_identifier: BigInt(1),
@@ -2199,10 +2262,10 @@ test.serial("verify set_approval_for_all(true) information.", async t => {
         (result as {Ok: Array<TokenMetadata>}).Ok.find(token_metadata => token_metadata.token_identifier === BigInt(2)),
         {
           owner: [johnIdentity.getPrincipal()],
-          operator: [bobIdentity.getPrincipal()],
+          operator: [],
           properties: [["B", {Int64Content: BigInt(1234)}]],
           is_burned: false,
-          approved_by: [johnIdentity.getPrincipal()],
+          approved_by: [aliceIdentity.getPrincipal()],
           burned_by: [],
           burned_at: [],
           token_identifier: BigInt(2),
@@ -2220,7 +2283,7 @@ test.serial("verify set_approval_for_all(true) information.", async t => {
         (result as {Ok: Array<TokenMetadata>}).Ok.find(token_metadata => token_metadata.token_identifier === BigInt(3)),
         {
           owner: [bobIdentity.getPrincipal()],
-          operator: [johnIdentity.getPrincipal()],
+          operator: [],
           properties: [["C", {Int32Content: 5678}]],
           is_burned: false,
           approved_by: [bobIdentity.getPrincipal()],
@@ -2235,10 +2298,10 @@ test.serial("verify set_approval_for_all(true) information.", async t => {
         (result as {Ok: Array<TokenMetadata>}).Ok.find(token_metadata => token_metadata.token_identifier === BigInt(4)),
         {
           owner: [bobIdentity.getPrincipal()],
-          operator: [johnIdentity.getPrincipal()],
+          operator: [],
           properties: [["D", {TextContent: "∆≈ç√∫"}]],
           is_burned: false,
-          approved_by: [bobIdentity.getPrincipal()],
+          approved_by: [johnIdentity.getPrincipal()],
           burned_by: [],
           burned_at: [],
           token_identifier: BigInt(4),
@@ -2263,111 +2326,33 @@ test.serial("verify set_approval_for_all(true) information.", async t => {
     }
   );
 
-  // verify operator_token_metadata
-  (
-    await Promise.all(allActors.map(actor => actor.dip721_operator_token_metadata(johnIdentity.getPrincipal())))
-  ).forEach(result => {
-    t.true("Ok" in result);
-    t.is((result as {Ok: Array<TokenMetadata>}).Ok.length, 2);
-    t.like(
-      (result as {Ok: Array<TokenMetadata>}).Ok.find(token_metadata => token_metadata.token_identifier === BigInt(4)),
-      {
-        owner: [bobIdentity.getPrincipal()],
-        operator: [johnIdentity.getPrincipal()],
-        properties: [["D", {TextContent: "∆≈ç√∫"}]],
-        is_burned: false,
-        approved_by: [bobIdentity.getPrincipal()],
-        burned_by: [],
-        burned_at: [],
-        token_identifier: BigInt(4),
-        minted_by: custodianIdentity.getPrincipal(),
-        transferred_by: [aliceIdentity.getPrincipal()]
-      }
-    );
-    t.like(
-      (result as {Ok: Array<TokenMetadata>}).Ok.find(token_metadata => token_metadata.token_identifier === BigInt(3)),
-      {
-        owner: [bobIdentity.getPrincipal()],
-        operator: [johnIdentity.getPrincipal()],
-        properties: [["C", {Int32Content: 5678}]],
-        is_burned: false,
-        approved_by: [bobIdentity.getPrincipal()],
-        burned_by: [],
-        burned_at: [],
-        token_identifier: BigInt(3),
-        minted_by: custodianIdentity.getPrincipal(),
-        transferred_by: [custodianIdentity.getPrincipal()]
-      }
-    );
-  });
-  (await Promise.all(allActors.map(actor => actor.dip721_operator_token_metadata(bobIdentity.getPrincipal())))).forEach(
-    result => {
-      t.true("Ok" in result);
-      t.is((result as {Ok: Array<TokenMetadata>}).Ok.length, 2);
-      t.like(
-        (result as {Ok: Array<TokenMetadata>}).Ok.find(token_metadata => token_metadata.token_identifier === BigInt(2)),
-        {
-          owner: [johnIdentity.getPrincipal()],
-          operator: [bobIdentity.getPrincipal()],
-          properties: [["B", {Int64Content: BigInt(1234)}]],
-          is_burned: false,
-          approved_by: [johnIdentity.getPrincipal()],
-          burned_by: [],
-          burned_at: [],
-          token_identifier: BigInt(2),
-          minted_by: custodianIdentity.getPrincipal(),
-          transferred_by: [custodianIdentity.getPrincipal()]
-        }
-      );
-      t.like(
-        (result as {Ok: Array<TokenMetadata>}).Ok.find(token_metadata => token_metadata.token_identifier === BigInt(1)),
-        {
-          owner: [johnIdentity.getPrincipal()],
-          operator: [bobIdentity.getPrincipal()],
-          properties: [["A", {Nat64Content: BigInt(9999)}]],
-          is_burned: false,
-          approved_by: [johnIdentity.getPrincipal()],
-          burned_by: [],
-          burned_at: [],
-          token_identifier: BigInt(1),
-          minted_by: custodianIdentity.getPrincipal(),
-          transferred_by: [custodianIdentity.getPrincipal()]
-        }
-      );
-    }
-  );
-
   // verify operator_token_identifiers
   (
     await Promise.all(allActors.map(actor => actor.dip721_operator_token_identifiers(johnIdentity.getPrincipal())))
-  ).forEach(result => {
-    t.true((result as {Ok: Array<bigint>}).Ok.includes(BigInt(3)));
-    t.true((result as {Ok: Array<bigint>}).Ok.includes(BigInt(4)));
-  });
+  ).forEach(result => t.deepEqual(result, {Err: {OperatorNotFound: null}}));
   (
     await Promise.all(allActors.map(actor => actor.dip721_operator_token_identifiers(bobIdentity.getPrincipal())))
-  ).forEach(result => {
-    t.true((result as {Ok: Array<bigint>}).Ok.includes(BigInt(1)));
-    t.true((result as {Ok: Array<bigint>}).Ok.includes(BigInt(2)));
-  });
+  ).forEach(result => t.deepEqual(result, {Err: {OperatorNotFound: null}}));
 });
 
 test.serial("error on self approve when set_approval_for_all.", async t => {
-  t.deepEqual(await johnActor.dip721_set_approval_for_all(johnIdentity.getPrincipal(), true), {
+  t.deepEqual(await johnActor.dip721_set_approval_for_all(johnIdentity.getPrincipal(), true, []), {
     Err: {SelfApprove: null}
   });
-  t.deepEqual(await bobActor.dip721_set_approval_for_all(bobIdentity.getPrincipal(), true), {Err: {SelfApprove: null}});
-  t.deepEqual(await johnActor.dip721_set_approval_for_all(johnIdentity.getPrincipal(), false), {
+  t.deepEqual(await bobActor.dip721_set_approval_for_all(bobIdentity.getPrincipal(), true, []), {
     Err: {SelfApprove: null}
   });
-  t.deepEqual(await bobActor.dip721_set_approval_for_all(bobIdentity.getPrincipal(), false), {
+  t.deepEqual(await johnActor.dip721_set_approval_for_all(johnIdentity.getPrincipal(), false, []), {
+    Err: {SelfApprove: null}
+  });
+  t.deepEqual(await bobActor.dip721_set_approval_for_all(bobIdentity.getPrincipal(), false, []), {
     Err: {SelfApprove: null}
   });
 });
 
 test.serial("set_approval_for_all(false).", async t => {
-  t.deepEqual(await bobActor.dip721_set_approval_for_all(johnIdentity.getPrincipal(), false), {Ok: BigInt(22)});
-  t.deepEqual(await johnActor.dip721_set_approval_for_all(bobIdentity.getPrincipal(), false), {Ok: BigInt(23)});
+  t.deepEqual(await bobActor.dip721_set_approval_for_all(johnIdentity.getPrincipal(), false, []), {Ok: BigInt(22)});
+  t.deepEqual(await johnActor.dip721_set_approval_for_all(bobIdentity.getPrincipal(), false, []), {Ok: BigInt(23)});
 
   // verify is_approved_for_all
   (
@@ -2827,12 +2812,16 @@ test.serial("error on update(burn) - burned NFTs.", async t => {
 
 test.serial("error on update(approve) - burned NFTs.", async t => {
   // approve
-  t.deepEqual(await aliceActor.dip721_approve(bobIdentity.getPrincipal(), BigInt(1)), {Err: {UnauthorizedOwner: null}});
-  t.deepEqual(await bobActor.dip721_approve(johnIdentity.getPrincipal(), BigInt(2)), {Err: {UnauthorizedOwner: null}});
-  t.deepEqual(await johnActor.dip721_approve(custodianIdentity.getPrincipal(), BigInt(3)), {
+  t.deepEqual(await aliceActor.dip721_approve(bobIdentity.getPrincipal(), BigInt(1), []), {
+    Err: {UnauthorizedOwner: null}
+  });
+  t.deepEqual(await bobActor.dip721_approve(johnIdentity.getPrincipal(), BigInt(2), []), {
     Err: {UnauthorizedOwner: null}
   });
-  t.deepEqual(await custodianActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(4)), {
+  t.deepEqual(await johnActor.dip721_approve(custodianIdentity.getPrincipal(), BigInt(3), []), {
+    Err: {UnauthorizedOwner: null}
+  });
+  t.deepEqual(await custodianActor.dip721_approve(aliceIdentity.getPrincipal(), BigInt(4), []), {
     Err: {UnauthorizedOwner: null}
   });
 });
@@ -2879,18 +2868,893 @@ test.serial("error on update(transfer_from) - burned NFTs.", async t => {
   );
 });
 
-test.serial("error on update(set_approval_for_all) - burned NFTs.", async t => {
-  // set_approval_for_all
-  t.deepEqual(await aliceActor.dip721_set_approval_for_all(bobIdentity.getPrincipal(), true), {
-    Err: {OwnerNotFound: null}
+test.serial("set_approval_for_all without owning any NFTs.", async t => {
+  t.deepEqual(await aliceActor.dip721_set_approval_for_all(bobIdentity.getPrincipal(), true, []), {Ok: BigInt(28)});
+  t.deepEqual(await bobActor.dip721_set_approval_for_all(johnIdentity.getPrincipal(), false, []), {Ok: BigInt(29)});
+  t.deepEqual(await johnActor.dip721_set_approval_for_all(custodianIdentity.getPrincipal(), true, []), {
+    Ok: BigInt(30)
+  });
+  t.deepEqual(await custodianActor.dip721_set_approval_for_all(aliceIdentity.getPrincipal(), false, []), {
+    Ok: BigInt(31)
+  });
+
+  // verify is_approved_for_all
+  (
+    await Promise.all([
+      ...allActors.map(actor =>
+        actor.dip721_is_approved_for_all(aliceIdentity.getPrincipal(), bobIdentity.getPrincipal())
+      ),
+      ...allActors.map(actor =>
+        actor.dip721_is_approved_for_all(johnIdentity.getPrincipal(), custodianIdentity.getPrincipal())
+      )
+    ])
+  ).forEach(result => t.deepEqual(result, {Ok: true}));
+  (
+    await Promise.all([
+      ...allActors.map(actor =>
+        actor.dip721_is_approved_for_all(bobIdentity.getPrincipal(), johnIdentity.getPrincipal())
+      ),
+      ...allActors.map(actor =>
+        actor.dip721_is_approved_for_all(custodianIdentity.getPrincipal(), aliceIdentity.getPrincipal())
+      )
+    ])
+  ).forEach(result => t.deepEqual(result, {Ok: false}));
+});
+
+test.serial("approve multiple operators and revoke one of them.", async t => {
+  t.deepEqual(await custodianActor.dip721_mint(aliceIdentity.getPrincipal(), BigInt(5), []), {Ok: BigInt(32)});
+  t.deepEqual(await aliceActor.dip721_approve(bobIdentity.getPrincipal(), BigInt(5), []), {Ok: BigInt(33)});
+  t.deepEqual(await aliceActor.dip721_approve(johnIdentity.getPrincipal(), BigInt(5), []), {Ok: BigInt(34)});
+
+  // both operators are kept, latest one is reported by operator_of
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(5))))).forEach(result => {
+    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal(), johnIdentity.getPrincipal()]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_operator_of(BigInt(5))))).forEach(result => {
+    t.deepEqual(result, {Ok: [johnIdentity.getPrincipal()]});
+  });
+
+  // revoke
+  t.deepEqual(await bobActor.dip721_revoke(johnIdentity.getPrincipal(), BigInt(5)), {Err: {UnauthorizedOwner: null}});
+  t.deepEqual(await aliceActor.dip721_revoke(custodianIdentity.getPrincipal(), BigInt(5)), {
+    Err: {OperatorNotFound: null}
+  });
+  t.deepEqual(await aliceActor.dip721_revoke(johnIdentity.getPrincipal(), BigInt(5)), {Ok: BigInt(35)});
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(5))))).forEach(result => {
+    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal()]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_operator_of(BigInt(5))))).forEach(result => {
+    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal()]});
   });
-  t.deepEqual(await bobActor.dip721_set_approval_for_all(johnIdentity.getPrincipal(), false), {
-    Err: {OwnerNotFound: null}
+  (
+    await Promise.all(allActors.map(actor => actor.dip721_operator_token_identifiers(johnIdentity.getPrincipal())))
+  ).forEach(result => t.deepEqual(result, {Err: {OperatorNotFound: null}}));
+
+  // revoked operator can't transfer anymore, remaining one can
+  t.deepEqual(
+    await johnActor.dip721_transfer_from(aliceIdentity.getPrincipal(), johnIdentity.getPrincipal(), BigInt(5)),
+    {
+      Err: {UnauthorizedOperator: null}
+    }
+  );
+  t.deepEqual(
+    await bobActor.dip721_transfer_from(aliceIdentity.getPrincipal(), bobIdentity.getPrincipal(), BigInt(5)),
+    {
+      Ok: BigInt(36)
+    }
+  );
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(5))))).forEach(result => {
+    t.deepEqual(result, {Ok: []});
   });
-  t.deepEqual(await johnActor.dip721_set_approval_for_all(custodianIdentity.getPrincipal(), true), {
-    Err: {OwnerNotFound: null}
+});
+
+test.serial("approval for all covers NFTs received after the approval.", async t => {
+  // alice approved bob for all before owning token 6
+  t.deepEqual(await custodianActor.dip721_mint(aliceIdentity.getPrincipal(), BigInt(6), []), {Ok: BigInt(37)});
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(6))))).forEach(result => {
+    t.deepEqual(result, {Ok: []});
   });
-  t.deepEqual(await custodianActor.dip721_set_approval_for_all(aliceIdentity.getPrincipal(), false), {
-    Err: {OwnerNotFound: null}
+  t.deepEqual(
+    await johnActor.dip721_transfer_from(aliceIdentity.getPrincipal(), johnIdentity.getPrincipal(), BigInt(6)),
+    {
+      Err: {UnauthorizedOperator: null}
+    }
+  );
+  t.deepEqual(
+    await bobActor.dip721_transfer_from(aliceIdentity.getPrincipal(), johnIdentity.getPrincipal(), BigInt(6)),
+    {
+      Ok: BigInt(38)
+    }
+  );
+
+  // transfers don't clear the approval for all of the previous owner
+  (
+    await Promise.all(
+      allActors.map(actor => actor.dip721_is_approved_for_all(aliceIdentity.getPrincipal(), bobIdentity.getPrincipal()))
+    )
+  ).forEach(result => t.deepEqual(result, {Ok: true}));
+
+  // revoked approval for all doesn't cover new NFTs
+  t.deepEqual(await aliceActor.dip721_set_approval_for_all(bobIdentity.getPrincipal(), false, []), {Ok: BigInt(39)});
+  t.deepEqual(await custodianActor.dip721_mint(aliceIdentity.getPrincipal(), BigInt(7), []), {Ok: BigInt(40)});
+  t.deepEqual(
+    await bobActor.dip721_transfer_from(aliceIdentity.getPrincipal(), bobIdentity.getPrincipal(), BigInt(7)),
+    {
+      Err: {UnauthorizedOperator: null}
+    }
+  );
+});
+
+test.serial("expired approvals are hidden and can't transfer.", async t => {
+  // expiry timestamps are in nanoseconds
+  const lapsedAt = BigInt(Date.now() - 1000) * BigInt(1_000_000);
+  t.deepEqual(await aliceActor.dip721_approve(johnIdentity.getPrincipal(), BigInt(7), [lapsedAt]), {
+    Err: {InvalidExpiry: null}
+  });
+  t.deepEqual(await aliceActor.dip721_set_approval_for_all(johnIdentity.getPrincipal(), true, [lapsedAt]), {
+    Err: {InvalidExpiry: null}
+  });
+
+  const expiresAt = BigInt(Date.now() + 20_000) * BigInt(1_000_000);
+  t.deepEqual(await aliceActor.dip721_approve(bobIdentity.getPrincipal(), BigInt(7), [expiresAt]), {Ok: BigInt(41)});
+  t.deepEqual(await aliceActor.dip721_approve(johnIdentity.getPrincipal(), BigInt(7), []), {Ok: BigInt(42)});
+  t.deepEqual(await aliceActor.dip721_set_approval_for_all(custodianIdentity.getPrincipal(), true, [expiresAt]), {
+    Ok: BigInt(43)
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(7))))).forEach(result => {
+    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal(), johnIdentity.getPrincipal()]});
+  });
+  (
+    await Promise.all(
+      allActors.map(actor =>
+        actor.dip721_is_approved_for_all(aliceIdentity.getPrincipal(), custodianIdentity.getPrincipal())
+      )
+    )
+  ).forEach(result => t.deepEqual(result, {Ok: true}));
+
+  // wait for both approvals to lapse
+  await new Promise(resolve => setTimeout(resolve, Number(expiresAt / BigInt(1_000_000)) - Date.now() + 2000));
+
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(7))))).forEach(result => {
+    t.deepEqual(result, {Ok: [johnIdentity.getPrincipal()]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_operator_of(BigInt(7))))).forEach(result => {
+    t.deepEqual(result, {Ok: [johnIdentity.getPrincipal()]});
+  });
+  (
+    await Promise.all(allActors.map(actor => actor.dip721_operator_token_identifiers(bobIdentity.getPrincipal())))
+  ).forEach(result => t.deepEqual(result, {Err: {OperatorNotFound: null}}));
+  (
+    await Promise.all(
+      allActors.map(actor =>
+        actor.dip721_is_approved_for_all(aliceIdentity.getPrincipal(), custodianIdentity.getPrincipal())
+      )
+    )
+  ).forEach(result => t.deepEqual(result, {Ok: false}));
+
+  t.deepEqual(
+    await bobActor.dip721_transfer_from(aliceIdentity.getPrincipal(), bobIdentity.getPrincipal(), BigInt(7)),
+    {
+      Err: {UnauthorizedOperator: null}
+    }
+  );
+  t.deepEqual(
+    await custodianActor.dip721_transfer_from(
+      aliceIdentity.getPrincipal(),
+      custodianIdentity.getPrincipal(),
+      BigInt(7)
+    ),
+    {
+      Err: {UnauthorizedOperator: null}
+    }
+  );
+  t.deepEqual(
+    await johnActor.dip721_transfer_from(aliceIdentity.getPrincipal(), johnIdentity.getPrincipal(), BigInt(7)),
+    {
+      Ok: BigInt(44)
+    }
+  );
+});
+
+test.serial("mint NFTs in a batch.", async t => {
+  // nothing is minted when any item is rejected
+  t.deepEqual(
+    await custodianActor.dip721_mint_batch([
+      {to: aliceIdentity.getPrincipal(), token_identifier: BigInt(8), properties: []},
+      {to: bobIdentity.getPrincipal(), token_identifier: BigInt(8), properties: []}
+    ]),
+    {Err: {ExistedNFT: null}}
+  );
+  t.deepEqual(
+    await custodianActor.dip721_mint_batch([
+      {to: aliceIdentity.getPrincipal(), token_identifier: BigInt(8), properties: []},
+      {to: bobIdentity.getPrincipal(), token_identifier: BigInt(5), properties: []}
+    ]),
+    {Err: {ExistedNFT: null}}
+  );
+  (await Promise.all(allActors.map(actor => actor.dip721_token_metadata(BigInt(8))))).forEach(result => {
+    t.deepEqual(result, {Err: {TokenNotFound: null}});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_total_transactions()))).forEach(result => {
+    t.is(result, BigInt(45));
+  });
+
+  t.deepEqual(
+    await custodianActor.dip721_mint_batch([
+      {to: aliceIdentity.getPrincipal(), token_identifier: BigInt(8), properties: [["E", {BoolContent: true}]]},
+      {to: bobIdentity.getPrincipal(), token_identifier: BigInt(9), properties: []}
+    ]),
+    {Ok: {start: BigInt(45), end: BigInt(47)}}
+  );
+  t.deepEqual(await custodianActor.dip721_mint_batch([]), {Ok: {start: BigInt(47), end: BigInt(47)}});
+
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_of(BigInt(8))))).forEach(result => {
+    t.deepEqual(result, {Ok: [aliceIdentity.getPrincipal()]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_of(BigInt(9))))).forEach(result => {
+    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal()]});
+  });
+  t.like(await aliceActor.dip721_transaction(BigInt(45)), {
+    Ok: {
+      caller: custodianIdentity.getPrincipal(),
+      operation: "mint",
+      details: [["to", {Principal: aliceIdentity.getPrincipal()}], ["token_identifier", {NatContent: BigInt(8)}]]
+    }
+  });
+  t.like(await aliceActor.dip721_transaction(BigInt(46)), {
+    Ok: {
+      caller: custodianIdentity.getPrincipal(),
+      operation: "mint",
+      details: [["to", {Principal: bobIdentity.getPrincipal()}], ["token_identifier", {NatContent: BigInt(9)}]]
+    }
+  });
+});
+
+test.serial("mint NFTs with sequential identifiers up to the max supply.", async t => {
+  // next identifier is one past the highest one
+  t.deepEqual(await custodianActor.dip721_mint_next(aliceIdentity.getPrincipal(), []), {Ok: BigInt(47)});
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_of(BigInt(10))))).forEach(result => {
+    t.deepEqual(result, {Ok: [aliceIdentity.getPrincipal()]});
+  });
+  t.like(await aliceActor.dip721_transaction(BigInt(47)), {
+    Ok: {
+      operation: "mint",
+      details: [["to", {Principal: aliceIdentity.getPrincipal()}], ["token_identifier", {NatContent: BigInt(10)}]]
+    }
+  });
+
+  await t.notThrowsAsync(custodianActor.dip721_set_max_supply([BigInt(11)]));
+  (await Promise.all(allActors.map(actor => actor.dip721_max_supply()))).forEach(result => {
+    t.deepEqual(result, [BigInt(11)]);
+  });
+  t.deepEqual(
+    await custodianActor.dip721_mint_batch([
+      {to: aliceIdentity.getPrincipal(), token_identifier: BigInt(12), properties: []},
+      {to: bobIdentity.getPrincipal(), token_identifier: BigInt(13), properties: []}
+    ]),
+    {Err: {MaxSupplyReached: null}}
+  );
+  t.deepEqual(await custodianActor.dip721_mint_next(bobIdentity.getPrincipal(), []), {Ok: BigInt(48)});
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_of(BigInt(11))))).forEach(result => {
+    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal()]});
+  });
+  t.deepEqual(await custodianActor.dip721_mint(aliceIdentity.getPrincipal(), BigInt(12), []), {
+    Err: {MaxSupplyReached: null}
+  });
+  t.deepEqual(await custodianActor.dip721_mint_next(aliceIdentity.getPrincipal(), []), {Err: {MaxSupplyReached: null}});
+  (await Promise.all(allActors.map(actor => actor.dip721_total_supply()))).forEach(result => {
+    t.is(result, BigInt(11));
+  });
+
+  await t.notThrowsAsync(custodianActor.dip721_set_max_supply([]));
+  (await Promise.all(allActors.map(actor => actor.dip721_max_supply()))).forEach(result => {
+    t.deepEqual(result, []);
+  });
+});
+
+test.serial("transfer NFTs in a batch.", async t => {
+  t.deepEqual(
+    await bobActor.dip721_transfer_batch([
+      {to: aliceIdentity.getPrincipal(), token_identifier: BigInt(5)},
+      {to: bobIdentity.getPrincipal(), token_identifier: BigInt(9)},
+      {to: johnIdentity.getPrincipal(), token_identifier: BigInt(8)},
+      {to: johnIdentity.getPrincipal(), token_identifier: BigInt(42)},
+      {to: johnIdentity.getPrincipal(), token_identifier: BigInt(11)},
+      {to: aliceIdentity.getPrincipal(), token_identifier: BigInt(11)}
+    ]),
+    [
+      {Ok: BigInt(49)},
+      {Err: {SelfTransfer: null}},
+      {Err: {UnauthorizedOwner: null}},
+      {Err: {TokenNotFound: null}},
+      {Ok: BigInt(50)},
+      {Err: {UnauthorizedOwner: null}}
+    ]
+  );
+
+  // verify owner caches
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_token_identifiers(bobIdentity.getPrincipal())))).forEach(
+    result => t.deepEqual(result, {Ok: [BigInt(9)]})
+  );
+  (
+    await Promise.all(allActors.map(actor => actor.dip721_owner_token_identifiers(aliceIdentity.getPrincipal())))
+  ).forEach(result => {
+    t.is((result as {Ok: Array<bigint>}).Ok.length, 3);
+    t.true((result as {Ok: Array<bigint>}).Ok.includes(BigInt(5)));
+  });
+  (
+    await Promise.all(allActors.map(actor => actor.dip721_owner_token_identifiers(johnIdentity.getPrincipal())))
+  ).forEach(result => {
+    t.is((result as {Ok: Array<bigint>}).Ok.length, 3);
+    t.true((result as {Ok: Array<bigint>}).Ok.includes(BigInt(11)));
+  });
+  t.like(await aliceActor.dip721_transaction(BigInt(50)), {
+    Ok: {
+      caller: bobIdentity.getPrincipal(),
+      operation: "transfer",
+      details: [
+        ["owner", {Principal: bobIdentity.getPrincipal()}],
+        ["to", {Principal: johnIdentity.getPrincipal()}],
+        ["token_identifier", {NatContent: BigInt(11)}]
+      ]
+    }
+  });
+});
+
+test.serial("transfer_from NFTs in a batch.", async t => {
+  // custodian is approved for all by john, bob is approved for token 8 only
+  t.deepEqual(await aliceActor.dip721_approve(bobIdentity.getPrincipal(), BigInt(8), []), {Ok: BigInt(51)});
+  t.deepEqual(
+    await custodianActor.dip721_transfer_from_batch([
+      {owner: johnIdentity.getPrincipal(), to: aliceIdentity.getPrincipal(), token_identifier: BigInt(6)},
+      {owner: johnIdentity.getPrincipal(), to: johnIdentity.getPrincipal(), token_identifier: BigInt(7)},
+      {owner: aliceIdentity.getPrincipal(), to: bobIdentity.getPrincipal(), token_identifier: BigInt(8)},
+      {owner: bobIdentity.getPrincipal(), to: aliceIdentity.getPrincipal(), token_identifier: BigInt(8)}
+    ]),
+    [
+      {Ok: BigInt(52)},
+      {Err: {SelfTransfer: null}},
+      {Err: {UnauthorizedOperator: null}},
+      {Err: {UnauthorizedOwner: null}}
+    ]
+  );
+  t.deepEqual(
+    await bobActor.dip721_transfer_from_batch([
+      {owner: aliceIdentity.getPrincipal(), to: bobIdentity.getPrincipal(), token_identifier: BigInt(8)}
+    ]),
+    [{Ok: BigInt(53)}]
+  );
+
+  // verify owner and operator caches
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_of(BigInt(6))))).forEach(result => {
+    t.deepEqual(result, {Ok: [aliceIdentity.getPrincipal()]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_of(BigInt(8))))).forEach(result => {
+    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal()]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(8))))).forEach(result => {
+    t.deepEqual(result, {Ok: []});
+  });
+  (
+    await Promise.all(allActors.map(actor => actor.dip721_operator_token_identifiers(bobIdentity.getPrincipal())))
+  ).forEach(result => t.deepEqual(result, {Err: {OperatorNotFound: null}}));
+});
+
+test.serial("transfer NFTs between subaccounts.", async t => {
+  const subaccount = Array(32).fill(1);
+  const aliceSubaccount = {owner: aliceIdentity.getPrincipal(), subaccount: [subaccount] as [Array<number>]};
+  t.deepEqual(await aliceActor.dip721_transfer_to_account([], aliceSubaccount, BigInt(5)), {Ok: BigInt(54)});
+
+  // verify accounts
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_of(BigInt(5))))).forEach(result => {
+    t.deepEqual(result, {Ok: [aliceIdentity.getPrincipal()]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_account_of(BigInt(5))))).forEach(result => {
+    t.deepEqual(result, {Ok: [aliceSubaccount]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_balance_of_account(aliceSubaccount)))).forEach(result => {
+    t.deepEqual(result, {Ok: BigInt(1)});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_token_identifiers_of_account(aliceSubaccount)))).forEach(
+    result => t.deepEqual(result, {Ok: [BigInt(5)]})
+  );
+  // principal endpoints map to the default subaccount, the all-zero subaccount is the default one too
+  (await Promise.all(allActors.map(actor => actor.dip721_balance_of(aliceIdentity.getPrincipal())))).forEach(result => {
+    t.deepEqual(result, {Ok: BigInt(2)});
+  });
+  (
+    await Promise.all(
+      allActors.map(actor =>
+        actor.dip721_balance_of_account({owner: aliceIdentity.getPrincipal(), subaccount: [Array(32).fill(0)]})
+      )
+    )
+  ).forEach(result => t.deepEqual(result, {Ok: BigInt(2)}));
+  (
+    await Promise.all(
+      allActors.map(actor =>
+        actor.dip721_balance_of_account({owner: aliceIdentity.getPrincipal(), subaccount: [[1, 2, 3]]})
+      )
+    )
+  ).forEach(result => t.deepEqual(result, {Err: {InvalidSubaccount: null}}));
+
+  // only the subaccount can move it
+  t.deepEqual(await aliceActor.dip721_transfer(bobIdentity.getPrincipal(), BigInt(5)), {
+    Err: {UnauthorizedOwner: null}
+  });
+  t.deepEqual(await aliceActor.dip721_transfer_to_account([subaccount], aliceSubaccount, BigInt(5)), {
+    Err: {SelfTransfer: null}
+  });
+  t.deepEqual(
+    await aliceActor.dip721_transfer_to_account(
+      [subaccount],
+      {owner: bobIdentity.getPrincipal(), subaccount: []},
+      BigInt(5)
+    ),
+    {Ok: BigInt(55)}
+  );
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_account_of(BigInt(5))))).forEach(result => {
+    t.deepEqual(result, {Ok: [{owner: bobIdentity.getPrincipal(), subaccount: []}]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_balance_of_account(aliceSubaccount)))).forEach(result => {
+    t.deepEqual(result, {Err: {OwnerNotFound: null}});
+  });
+  t.like(await aliceActor.dip721_transaction(BigInt(55)), {
+    Ok: {
+      caller: aliceIdentity.getPrincipal(),
+      operation: "transfer",
+      details: [
+        ["owner", {Principal: aliceIdentity.getPrincipal()}],
+        ["owner_subaccount", {BlobContent: subaccount}],
+        ["to", {Principal: bobIdentity.getPrincipal()}],
+        ["token_identifier", {NatContent: BigInt(5)}]
+      ]
+    }
+  });
+});
+
+test.serial("query and transfer NFTs through the ICRC-7 interface.", async t => {
+  const aliceAccount = {owner: aliceIdentity.getPrincipal(), subaccount: [] as []};
+  const bobAccount = {owner: bobIdentity.getPrincipal(), subaccount: [] as []};
+  const johnAccount = {owner: johnIdentity.getPrincipal(), subaccount: [] as []};
+
+  // verify ownership, burned nfts have no owner
+  (await Promise.all(allActors.map(actor => actor.icrc7_owner_of([BigInt(6), BigInt(1), BigInt(99)])))).forEach(
+    result => t.deepEqual(result, [[aliceAccount], [], []])
+  );
+  (await Promise.all(allActors.map(actor => actor.icrc7_balance_of([aliceAccount, bobAccount])))).forEach(result => {
+    t.deepEqual(result, [BigInt(2), BigInt(3)]);
+  });
+  (await Promise.all(allActors.map(actor => actor.icrc7_total_supply()))).forEach(result => {
+    t.is(result, BigInt(7));
+  });
+  (await Promise.all(allActors.map(actor => actor.icrc7_tokens([BigInt(8)], [BigInt(2)])))).forEach(result => {
+    t.deepEqual(result, [BigInt(9), BigInt(10)]);
+  });
+  // burned nfts are skipped, the unburned ones add up to the total supply
+  (await Promise.all(allActors.map(actor => actor.icrc7_tokens([], [BigInt(100)])))).forEach(result => {
+    t.is(result.length, 7);
+  });
+  (await Promise.all(allActors.map(actor => actor.icrc7_tokens_of(bobAccount, [], [])))).forEach(result => {
+    t.deepEqual(result, [BigInt(5), BigInt(8), BigInt(9)]);
+  });
+  (await Promise.all(allActors.map(actor => actor.icrc7_tokens_of(bobAccount, [BigInt(5)], [BigInt(1)])))).forEach(
+    result => t.deepEqual(result, [BigInt(8)])
+  );
+  (await Promise.all(allActors.map(actor => actor.icrc7_supported_standards()))).forEach(result => {
+    t.deepEqual(result, [
+      {name: "ICRC-7", url: "https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-7/ICRC-7.md"},
+      {name: "ICRC-3", url: "https://github.com/dfinity/ICRC-1/blob/main/standards/ICRC-3/README.md"},
+      {name: "ICRC-37", url: "https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-37/ICRC-37.md"}
+    ]);
+  });
+
+  // transfers are not atomic
+  const createdAtTime = BigInt(Date.now()) * BigInt(1_000_000);
+  const transferArg = {
+    from_subaccount: [] as [],
+    to: johnAccount,
+    token_id: BigInt(6),
+    memo: [[1, 2, 3]] as [Array<number>],
+    created_at_time: [createdAtTime] as [bigint]
+  };
+  t.deepEqual(
+    await aliceActor.icrc7_transfer([
+      transferArg,
+      {...transferArg, token_id: BigInt(8), created_at_time: []},
+      {...transferArg, token_id: BigInt(1), created_at_time: []}
+    ]),
+    [[{Ok: BigInt(56)}], [{Err: {Unauthorized: null}}], [{Err: {NonExistingTokenId: null}}]]
+  );
+  t.deepEqual(await aliceActor.icrc7_transfer([transferArg]), [[{Err: {Duplicate: {duplicate_of: BigInt(56)}}}]]);
+  t.deepEqual(await aliceActor.icrc7_transfer([{...transferArg, created_at_time: [BigInt(1)]}]), [
+    [{Err: {TooOld: null}}]
+  ]);
+  t.deepEqual(await aliceActor.icrc7_transfer([{...transferArg, to: aliceAccount, created_at_time: []}]), [
+    [{Err: {InvalidRecipient: null}}]
+  ]);
+
+  // verify ownership
+  (await Promise.all(allActors.map(actor => actor.icrc7_owner_of([BigInt(6)])))).forEach(result => {
+    t.deepEqual(result, [[johnAccount]]);
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_of(BigInt(6))))).forEach(result => {
+    t.deepEqual(result, {Ok: [johnIdentity.getPrincipal()]});
+  });
+  t.like(await aliceActor.dip721_transaction(BigInt(56)), {
+    Ok: {
+      caller: aliceIdentity.getPrincipal(),
+      operation: "transfer",
+      details: [
+        ["owner", {Principal: aliceIdentity.getPrincipal()}],
+        ["to", {Principal: johnIdentity.getPrincipal()}],
+        ["token_identifier", {NatContent: BigInt(6)}],
+        ["memo", {BlobContent: [1, 2, 3]}],
+        ["created_at_time", {Nat64Content: createdAtTime}]
+      ]
+    }
+  });
+});
+
+test.serial("approve and transfer_from NFTs through the ICRC-37 interface.", async t => {
+  const aliceAccount = {owner: aliceIdentity.getPrincipal(), subaccount: [] as []};
+  const bobAccount = {owner: bobIdentity.getPrincipal(), subaccount: [] as []};
+  const johnAccount = {owner: johnIdentity.getPrincipal(), subaccount: [] as []};
+  const custodianAccount = {owner: custodianIdentity.getPrincipal(), subaccount: [] as []};
+  const approvalInfo = {
+    spender: bobAccount,
+    from_subaccount: [] as [],
+    expires_at: [] as [],
+    memo: [] as [],
+    created_at_time: BigInt(Date.now()) * BigInt(1_000_000)
+  };
+
+  // token approvals are shared with dip721 operators
+  t.deepEqual(await aliceActor.icrc37_approve_tokens([{token_id: BigInt(10), approval_info: approvalInfo}]), [
+    [{Ok: BigInt(57)}]
+  ]);
+  t.deepEqual(
+    await aliceActor.icrc37_approve_tokens([
+      {token_id: BigInt(5), approval_info: approvalInfo},
+      {token_id: BigInt(10), approval_info: {...approvalInfo, spender: aliceAccount}}
+    ]),
+    [[{Err: {Unauthorized: null}}], [{Err: {InvalidSpender: null}}]]
+  );
+  (await Promise.all(allActors.map(actor => actor.dip721_operators_of(BigInt(10))))).forEach(result => {
+    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal()]});
+  });
+  (
+    await Promise.all(
+      allActors.map(actor =>
+        actor.icrc37_is_approved([
+          {spender: bobAccount, from_subaccount: [], token_id: BigInt(10)},
+          {spender: johnAccount, from_subaccount: [], token_id: BigInt(10)}
+        ])
+      )
+    )
+  ).forEach(result => t.deepEqual(result, [true, false]));
+  (await Promise.all(allActors.map(actor => actor.icrc37_get_token_approvals(BigInt(10), [], [])))).forEach(result => {
+    t.deepEqual(result.map(approval => approval.approval_info.spender), [bobAccount]);
+  });
+  t.deepEqual(
+    await aliceActor.icrc37_revoke_token_approvals([
+      {spender: [bobAccount], from_subaccount: [], token_id: BigInt(10), memo: [], created_at_time: []}
+    ]),
+    [[{Ok: BigInt(58)}]]
+  );
+  t.deepEqual(
+    await aliceActor.icrc37_revoke_token_approvals([
+      {spender: [bobAccount], from_subaccount: [], token_id: BigInt(10), memo: [], created_at_time: []}
+    ]),
+    [[{Err: {ApprovalDoesNotExist: null}}]]
+  );
+
+  // collection approvals are shared with dip721 approvals for all, john approved custodian earlier
+  (
+    await Promise.all(
+      allActors.map(actor =>
+        actor.icrc37_is_approved([{spender: custodianAccount, from_subaccount: [], token_id: BigInt(11)}])
+      )
+    )
+  ).forEach(result => t.deepEqual(result, [true]));
+  const transferFromArg = {
+    spender_subaccount: [] as [],
+    from: johnAccount,
+    to: aliceAccount,
+    token_id: BigInt(11),
+    memo: [] as [],
+    created_at_time: [] as []
+  };
+  t.deepEqual(await bobActor.icrc37_transfer_from([transferFromArg]), [[{Err: {Unauthorized: null}}]]);
+  t.deepEqual(await custodianActor.icrc37_transfer_from([transferFromArg]), [[{Ok: BigInt(59)}]]);
+  (await Promise.all(allActors.map(actor => actor.icrc7_owner_of([BigInt(11)])))).forEach(result => {
+    t.deepEqual(result, [[aliceAccount]]);
+  });
+
+  t.deepEqual(await aliceActor.icrc37_approve_collection([{approval_info: approvalInfo}]), [[{Ok: BigInt(60)}]]);
+  (
+    await Promise.all(
+      allActors.map(actor => actor.dip721_is_approved_for_all(aliceIdentity.getPrincipal(), bobIdentity.getPrincipal()))
+    )
+  ).forEach(result => t.deepEqual(result, {Ok: true}));
+  t.deepEqual(
+    await aliceActor.icrc37_revoke_collection_approvals([
+      {spender: [], from_subaccount: [], memo: [], created_at_time: []}
+    ]),
+    [[{Ok: BigInt(61)}]]
+  );
+  (
+    await Promise.all(
+      allActors.map(actor => actor.dip721_is_approved_for_all(aliceIdentity.getPrincipal(), bobIdentity.getPrincipal()))
+    )
+  ).forEach(result => t.deepEqual(result, {Ok: false}));
+});
+
+test.serial("verify the ICRC-3 block log.", async t => {
+  const {log_length, blocks, archived_blocks} = await aliceActor.icrc3_get_blocks([
+    {start: BigInt(0), length: BigInt(2)},
+    {start: BigInt(61), length: BigInt(10)}
+  ]);
+  t.is(log_length, BigInt(62));
+  t.deepEqual(archived_blocks, []);
+  t.deepEqual(blocks.map(({id}) => id), [BigInt(0), BigInt(1), BigInt(61)]);
+
+  // every block but the first one is chained to its parent
+  const fields = blocks.map(({block}) => new Map("Map" in block ? block.Map : []));
+  t.deepEqual(fields[0].get("btype"), {Text: "7mint"});
+  t.false(fields[0].has("phash"));
+  const phash = fields[1].get("phash");
+  t.true(phash !== undefined && "Blob" in phash && phash.Blob.length === 32);
+  t.deepEqual(fields[2].get("btype"), {Text: "37revoke_coll"});
+
+  // the tip is certified
+  (await Promise.all(allActors.map(actor => actor.icrc3_get_tip_certificate()))).forEach(result => {
+    t.is(result.length, 1);
+  });
+  (await Promise.all(allActors.map(actor => actor.icrc3_supported_block_types()))).forEach(result => {
+    t.true(result.some(({block_type}) => block_type === "7xfer"));
+  });
+});
+
+test.serial("transfer NFTs with a notification, returned when the receiver canister doesn't accept them.", async t => {
+  // users aren't notified
+  t.deepEqual(await aliceActor.dip721_transfer_and_notify(bobIdentity.getPrincipal(), BigInt(11), []), {Ok: BigInt(62)});
+
+  // the nft canister doesn't implement on_dip721_received
+  t.deepEqual(await bobActor.dip721_transfer_and_notify(Actor.canisterIdOf(bobActor), BigInt(11), [1, 2, 3]), {
+    Err: {TransferRejected: null}
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_of(BigInt(11))))).forEach(result => {
+    t.deepEqual(result, {Ok: [bobIdentity.getPrincipal()]});
+  });
+  const returned = await bobActor.dip721_transaction(BigInt(64));
+  t.true("Ok" in returned);
+  if ("Ok" in returned) {
+    t.is(returned.Ok.operation, "transfer");
+    t.deepEqual(new Map(returned.Ok.details).get("rejected_tx_id"), {NatContent: BigInt(63)});
+    t.deepEqual(new Map(returned.Ok.details).get("to"), {Principal: bobIdentity.getPrincipal()});
+  }
+});
+
+test.serial("subscribe canisters to events, delivered from the global timer.", async t => {
+  const subscriber = Actor.canisterIdOf(custodianActor);
+
+  // only custodians manage subscriptions
+  (
+    await Promise.allSettled(normalActors.map(actor => actor.dip721_subscribe(subscriber, [{Transfer: null}])))
+  ).forEach(promise => t.is(promise.status, "rejected"));
+
+  await custodianActor.dip721_subscribe(subscriber, [{Transfer: null}]);
+  (await Promise.all(allActors.map(actor => actor.dip721_subscribers()))).forEach(result => {
+    t.is(result.length, 1);
+    t.is(result[0][0].toText(), subscriber.toText());
+    t.deepEqual(result[0][1].event_types, [{Transfer: null}]);
+    t.is(result[0][1].cursor, BigInt(65));
+    t.is(result[0][1].failures, 0);
+  });
+
+  // the nft canister doesn't implement on_dip721_events, the delivery is retried later
+  t.deepEqual(await bobActor.dip721_transfer(johnIdentity.getPrincipal(), BigInt(11)), {Ok: BigInt(65)});
+  await new Promise(resolve => setTimeout(resolve, 5000));
+  const [[, state]] = await custodianActor.dip721_subscribers();
+  t.is(state.cursor, BigInt(65));
+  t.true(state.failures >= 1);
+  t.true(state.retry_at > BigInt(0));
+
+  (
+    await Promise.allSettled(normalActors.map(actor => actor.dip721_unsubscribe(subscriber)))
+  ).forEach(promise => t.is(promise.status, "rejected"));
+  await custodianActor.dip721_unsubscribe(subscriber);
+  (await Promise.all(allActors.map(actor => actor.dip721_subscribers()))).forEach(result => {
+    t.deepEqual(result, []);
+  });
+});
+
+test.serial("flush the cap outbox, every tx reaches cap in order.", async t => {
+  // the global timer flushes the outbox
+  for (let i = 0; i < 10 && (await custodianActor.dip721_cap_outbox()).pending > BigInt(0); i++) {
+    await new Promise(resolve => setTimeout(resolve, 1000));
+  }
+  (await Promise.all(allActors.map(actor => actor.dip721_cap_outbox()))).forEach(result => {
+    t.deepEqual(result, {pending: BigInt(0), failed: 0, retry_at: [], last_error: [], resync_cursor: []});
+  });
+
+  // only custodians force a flush
+  (await Promise.allSettled(normalActors.map(actor => actor.dip721_flush_cap_outbox()))).forEach(promise =>
+    t.is(promise.status, "rejected")
+  );
+  t.deepEqual(await custodianActor.dip721_flush_cap_outbox(), {
+    pending: BigInt(0),
+    failed: 0,
+    retry_at: [],
+    last_error: [],
+    resync_cursor: []
+  });
+
+  const txns = await testTxns([
+    {
+      id: "65",
+      tx: {
+        caller: bobIdentity.getPrincipal(),
+        operation: "transfer",
+        details: {
+          token_identifier: {Text: "11"},
+          to: {Principal: johnIdentity.getPrincipal()}
+        }
+      }
+    }
+  ]);
+  txns.map(({txResp, tx}) => {
+    t.like(txResp, tx);
+  });
+});
+
+test.serial("re-sync cap from the tx log.", async t => {
+  // only custodians re-sync
+  (await Promise.allSettled(normalActors.map(actor => actor.dip721_resync_cap([BigInt(0)], 10)))).forEach(promise =>
+    t.is(promise.status, "rejected")
+  );
+
+  // nothing to resume
+  t.deepEqual(await custodianActor.dip721_resync_cap([], 10), {Err: {Other: "no cap re-sync to resume"}});
+  t.deepEqual(await custodianActor.dip721_resync_cap([BigInt(67)], 10), {
+    Err: {Other: "can't re-sync cap from a tx after `tx_count`"}
+  });
+
+  // the root bucket already holds every tx
+  t.deepEqual(await custodianActor.dip721_resync_cap([BigInt(66)], 10), {Ok: BigInt(66)});
+  (await Promise.all(allActors.map(actor => actor.dip721_cap_outbox()))).forEach(result => {
+    t.deepEqual(result.resync_cursor, []);
+    t.deepEqual(result.last_error, []);
+  });
+});
+
+
+test.serial("flush the cap outbox to an unreachable cap, the flushes back off.", async t => {
+  await custodianActor.dip721_set_cap([Principal.fromText(capStubCanisterId)]);
+  t.deepEqual(await johnActor.dip721_approve(bobIdentity.getPrincipal(), BigInt(11), []), {Ok: BigInt(66)});
+
+  // the timer flush fails and backs off, the tx stays queued
+  for (let i = 0; i < 10 && (await custodianActor.dip721_cap_outbox()).failed === 0; i++) {
+    await new Promise(resolve => setTimeout(resolve, 1000));
+  }
+  const failed = await custodianActor.dip721_cap_outbox();
+  t.is(failed.pending, BigInt(1));
+  t.true(failed.failed > 0);
+  t.is(failed.retry_at.length, 1);
+  t.is(failed.last_error.length, 1);
+
+  // a forced flush skips the backoff, which doubles on every failure
+  const refailed = await custodianActor.dip721_flush_cap_outbox();
+  t.is(refailed.pending, BigInt(1));
+  t.is(refailed.failed, failed.failed + 1);
+  t.true((refailed.retry_at[0] ?? BigInt(0)) > (failed.retry_at[0] ?? BigInt(0)));
+
+  // back to the cap router, the backoff still holds the timer flushes
+  await custodianActor.dip721_set_cap([Principal.fromText(capCanisterId)]);
+});
+
+test.serial("re-sync cap during a flush, the re-sync is refused.", async t => {
+  // queued behind the failed tx while the flushes back off
+  const approvals = await Promise.all(
+    [...Array(10)].map(() => johnActor.dip721_approve(bobIdentity.getPrincipal(), BigInt(11), []))
+  );
+  t.deepEqual(
+    approvals.map(result => ("Ok" in result ? result.Ok : BigInt(-1))).sort(),
+    [...Array(10)].map((_, i) => BigInt(67 + i))
+  );
+
+  // the flush awaits cap for every tx, the re-sync lands in between
+  const flush = custodianActor.dip721_flush_cap_outbox();
+  await new Promise(resolve => setTimeout(resolve, 500));
+  t.deepEqual(await custodianActor.dip721_resync_cap([BigInt(0)], 10), {
+    Err: {Other: "a cap flush is running, re-sync once it's over"}
+  });
+  await flush;
+
+  for (let i = 0; i < 10 && (await custodianActor.dip721_cap_outbox()).pending > BigInt(0); i++) {
+    await new Promise(resolve => setTimeout(resolve, 1000));
+  }
+  (await Promise.all(allActors.map(actor => actor.dip721_cap_outbox()))).forEach(result => {
+    t.deepEqual(result, {pending: BigInt(0), failed: 0, retry_at: [], last_error: [], resync_cursor: []});
+  });
+
+  // every tx reached cap under its own id
+  const txns = await testTxns(
+    [...Array(11)].map((_, i) => ({
+      id: `${66 + i}`,
+      tx: {
+        caller: johnIdentity.getPrincipal(),
+        operation: "approve",
+        details: {
+          operator: {Principal: bobIdentity.getPrincipal()},
+          token_identifier: {Text: "11"}
+        }
+      }
+    }))
+  );
+  txns.map(({txResp, tx}) => {
+    t.like(txResp, tx);
+  });
+});
+
+test.serial("switch to a new cap router and re-sync, every tx lands in its root bucket.", async t => {
+  await custodianActor.dip721_set_cap([Principal.fromText(nextCapCanisterId)]);
+  (await Promise.all(allActors.map(actor => actor.dip721_metadata()))).forEach(result => {
+    t.is(result.cap[0]?.toText(), nextCapCanisterId);
+  });
+
+  // the next flush creates the root bucket on the new router, the re-sync fills it from the first tx
+  t.deepEqual(await custodianActor.dip721_resync_cap([BigInt(0)], 1000), {Ok: BigInt(77)});
+  for (let i = 0; i < 120 && (await custodianActor.dip721_cap_outbox()).pending > BigInt(0); i++) {
+    await new Promise(resolve => setTimeout(resolve, 1000));
+  }
+  (await Promise.all(allActors.map(actor => actor.dip721_cap_outbox()))).forEach(result => {
+    t.deepEqual(result, {pending: BigInt(0), failed: 0, retry_at: [], last_error: [], resync_cursor: []});
+  });
+
+  const {canister: bucketResponse} = await nextCapRouter.get_token_contract_root_bucket({
+    tokenId: Principal.fromText(nftCanisterId),
+    witness: false
+  });
+  const nextRootBucket = bucketResponse.toString();
+  t.true(nextRootBucket.length > 0);
+  t.not(nextRootBucket, capRootBucket);
+
+  const nextRoot = await CapRoot.init({host, canisterId: nextRootBucket});
+  for (const txId of [BigInt(0), BigInt(76)]) {
+    const resp = await nextRoot.get_transaction(txId);
+    const {caller, operation} = resp.Found[0][0];
+    t.like(await aliceActor.dip721_transaction(txId), {Ok: {caller, operation}});
+  }
+
+  // back to the first router, which already holds every tx
+  await custodianActor.dip721_set_cap([Principal.fromText(capCanisterId)]);
+});
+
+test.serial("upgrade the canister, the stable state is kept and migrated.", async t => {
+  const {upgraded_at} = await custodianActor.dip721_metadata();
+
+  // upgrade args are given with the init type, only `cap` is read
+  const upgradeArgs = `(opt record {cap = opt principal "${capCanisterId}"})`;
+  execSync(`dfx canister install nft --mode upgrade --argument '${upgradeArgs}'`, {cwd: ".."});
+
+  (await Promise.all(allActors.map(actor => actor.dip721_metadata()))).forEach(result => {
+    t.true(result.upgraded_at > upgraded_at);
+    t.deepEqual(result.history, {Both: null});
+    t.is(result.cap[0]?.toText(), capCanisterId);
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_total_transactions()))).forEach(result => {
+    t.is(result, BigInt(77));
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_owner_of(BigInt(11))))).forEach(result => {
+    t.deepEqual(result, {Ok: [johnIdentity.getPrincipal()]});
+  });
+  (await Promise.all(allActors.map(actor => actor.dip721_transaction(BigInt(65))))).forEach(result => {
+    t.like(result, {Ok: {operation: "transfer", caller: bobIdentity.getPrincipal()}});
+  });
+
+  // certified data is restored
+  (await Promise.all(allActors.map(actor => actor.icrc3_get_tip_certificate()))).forEach(result => {
+    t.is(result.length, 1);
   });
 });
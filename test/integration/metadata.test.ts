@@ -1,6 +1,7 @@
+import {Principal} from "@dfinity/principal";
 import test, {Assertions} from "ava";
 
-import {aliceActor, bobActor, custodianActor, custodianIdentity, johnActor} from "../setup";
+import {aliceActor, bobActor, capCanisterId, custodianActor, custodianIdentity, johnActor} from "../setup";
 
 const normalActors = [aliceActor, bobActor, johnActor];
 const allActors = [...normalActors, custodianActor];
@@ -43,16 +44,30 @@ test("CRUD custodians", async (t: Assertions) => {
 
 test("interfaces", async (t: Assertions) => {
   (await Promise.all(allActors.map(actor => actor.dip721_supported_interfaces()))).forEach(result => {
-    t.deepEqual(result, [{Approval: null}, {Mint: null}, {Burn: null}]);
+    t.deepEqual(result, [{Approval: null}, {Mint: null}, {Burn: null}, {TransactionHistory: null}]);
   });
 });
 
+test("CRUD cap", async (t: Assertions) => {
+  const cap = Principal.fromText(capCanisterId);
+  (await Promise.all(allActors.map(actor => actor.dip721_metadata()))).forEach(result =>
+    t.deepEqual(result.cap, [cap])
+  );
+  // the router hands out the same root bucket again
+  await t.notThrowsAsync(custodianActor.dip721_set_cap([cap]));
+  (await Promise.all(allActors.map(actor => actor.dip721_metadata()))).forEach(result =>
+    t.deepEqual(result.cap, [cap])
+  );
+});
+
 test("metadata", async t => {
   (await Promise.all(allActors.map(actor => actor.dip721_metadata()))).forEach(result => {
     t.deepEqual(result.name, ["nft"]);
     t.deepEqual(result.logo, ["nftLogo"]);
     t.deepEqual(result.symbol, ["nftSymbol"]);
     t.deepEqual(result.custodians, [custodianIdentity.getPrincipal()]);
+    // installed without `history`, txs are logged and forwarded to cap
+    t.deepEqual(result.history, {Both: null});
   });
 });
 
@@ -69,8 +84,16 @@ test("error on unauthorized metadata update", async t => {
   (await Promise.allSettled(normalActors.map(actor => actor.dip721_set_symbol("nftSymbol")))).forEach(promise =>
     t.is(promise.status, "rejected")
   );
+  // setMaxSupply error when caller is not an custodian
+  (await Promise.allSettled(normalActors.map(actor => actor.dip721_set_max_supply([])))).forEach(promise =>
+    t.is(promise.status, "rejected")
+  );
   // setCustodians error when caller is not an custodian
   (await Promise.allSettled(normalActors.map(actor => actor.dip721_set_custodians([])))).forEach(promise =>
     t.is(promise.status, "rejected")
   );
+  // setCap error when caller is not an custodian
+  (await Promise.allSettled(normalActors.map(actor => actor.dip721_set_cap([])))).forEach(promise =>
+    t.is(promise.status, "rejected")
+  );
 });
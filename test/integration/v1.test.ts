@@ -0,0 +1,54 @@
+import test from "ava";
+
+import {MetadataPart} from "../factory/compat/idl.d";
+import {aliceCompatActor, aliceIdentity, bobCompatActor, bobIdentity, custodianCompatActor} from "../setup";
+
+const metadata: MetadataPart[] = [
+  {
+    purpose: {Rendered: null},
+    key_val_data: [
+      {key: "name", val: {TextContent: "v1"}},
+      {key: "rarity", val: {Nat8Content: 3}}
+    ],
+    data: []
+  }
+];
+
+const alice = aliceIdentity.getPrincipal();
+const bob = bobIdentity.getPrincipal();
+
+let tokenId: bigint;
+
+test.serial("mint NFT with the v1 methods and verify information.", async t => {
+  t.deepEqual(await aliceCompatActor.mintDip721(alice, metadata), {Err: {Unauthorized: null}});
+
+  const balance = await aliceCompatActor.balanceOfDip721(alice);
+  const result = await custodianCompatActor.mintDip721(alice, metadata);
+  if (!("Ok" in result)) {
+    return t.fail("mintDip721 failed");
+  }
+  tokenId = result.Ok.token_id;
+
+  t.deepEqual(await aliceCompatActor.ownerOfDip721(tokenId), {Ok: alice});
+  t.is(await aliceCompatActor.balanceOfDip721(alice), balance + BigInt(1));
+  t.true((await aliceCompatActor.getTokenIdsForUserDip721(alice)).includes(tokenId));
+  t.deepEqual(await aliceCompatActor.getMetadataDip721(tokenId), {Ok: metadata});
+  t.deepEqual(await aliceCompatActor.getMetadataDip721(tokenId + BigInt(1)), {Err: {InvalidTokenId: null}});
+});
+
+test.serial("transfer NFT with the v1 methods and verify information.", async t => {
+  // bob isn't the owner nor an operator
+  t.deepEqual(await bobCompatActor.transferFromDip721(alice, bob, tokenId), {Err: {Unauthorized: null}});
+
+  t.true("Ok" in (await aliceCompatActor.transferFromDip721(alice, bob, tokenId)));
+  t.deepEqual(await aliceCompatActor.ownerOfDip721(tokenId), {Ok: bob});
+  t.false((await aliceCompatActor.getTokenIdsForUserDip721(alice)).includes(tokenId));
+  t.deepEqual(await bobCompatActor.getTokenIdsForUserDip721(bob), [tokenId]);
+  t.deepEqual(await bobCompatActor.getMetadataForUserDip721(bob), [{token_id: tokenId, metadata_desc: metadata}]);
+});
+
+test.serial("verify v1 mint rejections keep their own message.", async t => {
+  await custodianCompatActor.dip721_set_max_supply([await custodianCompatActor.totalSupplyDip721()]);
+  await t.throwsAsync(custodianCompatActor.mintDip721(alice, metadata), {message: /Max supply reached/});
+  await custodianCompatActor.dip721_set_max_supply([]);
+});
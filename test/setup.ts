@@ -1,10 +1,13 @@
 import {readFileSync} from "fs";
 
-import {Actor, HttpAgent, Identity} from "@dfinity/agent";
+import {Actor, ActorMethod, HttpAgent, Identity} from "@dfinity/agent";
+import {IDL} from "@dfinity/candid";
 import {Ed25519KeyIdentity} from "@dfinity/identity";
 import {CapRouter} from "@psychedelic/cap-js";
 import fetch from "isomorphic-fetch";
 
+import {idlFactory as compatIdlFactory} from "./factory/compat/idl";
+import {_SERVICE as CompatService} from "./factory/compat/idl.d";
 import {idlFactory} from "./factory/idl";
 import {_SERVICE as Service} from "./factory/idl.d";
 
@@ -23,12 +26,26 @@ const canister_ids = JSON.parse(readFileSync("../.dfx/local/canister_ids.json",
 
 export const nftCanisterId = canister_ids["nft"].local as string;
 export const capCanisterId = canister_ids["cap-router"].local as string;
+// second cap router the tests switch to, see `cap-router-next` in dfx.json
+export const nextCapCanisterId = canister_ids["cap-router-next"].local as string;
+// cap router whose root bucket rejects every insert, see `examples/cap_stub.rs`
+export const capStubCanisterId = canister_ids["cap-stub"].local as string;
+// built with the compatibility features, see `nft-compat` in dfx.json
+export const compatCanisterId = canister_ids["nft-compat"].local as string;
+// same wasm as `nft-compat`, installed with the tx log disabled
+export const unloggedCanisterId = canister_ids["nft-unlogged"].local as string;
+// registers the hooks of `examples/hooked.rs`
+export const hookedCanisterId = canister_ids["nft-hooked"].local as string;
 
-const createActor = async (identity: Identity): Promise<Service> => {
+const createActor = async <T = Service>(
+  identity: Identity,
+  factory: IDL.InterfaceFactory = idlFactory,
+  canisterId = nftCanisterId
+): Promise<T> => {
   const agent = new HttpAgent({host, fetch, identity});
 
-  const actor = Actor.createActor<Service>(idlFactory, {
-    canisterId: nftCanisterId,
+  const actor = Actor.createActor<T>(factory, {
+    canisterId,
     agent
   });
 
@@ -46,7 +63,37 @@ export const bobActor = await createActor(bobIdentity);
 export const johnActor = await createActor(johnIdentity);
 export const custodianActor = await createActor(custodianIdentity);
 
+export const aliceCompatActor = await createActor<CompatService>(aliceIdentity, compatIdlFactory, compatCanisterId);
+export const bobCompatActor = await createActor<CompatService>(bobIdentity, compatIdlFactory, compatCanisterId);
+export const custodianCompatActor = await createActor<CompatService>(
+  custodianIdentity,
+  compatIdlFactory,
+  compatCanisterId
+);
+
+export const custodianUnloggedActor = await createActor<CompatService>(
+  custodianIdentity,
+  compatIdlFactory,
+  unloggedCanisterId
+);
+
+export const aliceHookedActor = await createActor(aliceIdentity, idlFactory, hookedCanisterId);
+export const bobHookedActor = await createActor(bobIdentity, idlFactory, hookedCanisterId);
+export const custodianHookedActor = await createActor(custodianIdentity, idlFactory, hookedCanisterId);
+// `hook_log` is only exported by the example
+const hookLogIdlFactory: IDL.InterfaceFactory = ({IDL}) =>
+  IDL.Service({hook_log: IDL.Func([], [IDL.Vec(IDL.Text)], ["query"])});
+export const hookLogActor = await createActor<{hook_log: ActorMethod<[], string[]>}>(
+  aliceIdentity,
+  hookLogIdlFactory,
+  hookedCanisterId
+);
+
 export const capRouter = await CapRouter.init({
   canisterId: capCanisterId,
   host
 });
+export const nextCapRouter = await CapRouter.init({
+  canisterId: nextCapCanisterId,
+  host
+});